@@ -1,62 +1,74 @@
+use gnarled::attributes::AttributeSVGLayer;
+use gnarled::n2::bounds::Bounds;
 use gnarled::n2::polyline::PolyLine;
 
-use gnarled::svg::SVGable;
+use gnarled::svg::{SvgDocument, SvgLayers};
 
 use gnarled::n2::point::p2;
-use gnarled::svg::{PolyLineProperties, PolyLineStroke};
+use gnarled::style::{Colour, StrokeStyle};
 
 pub fn main() -> Result<(), std::io::Error> {
-    use std::io::Write;
-
     let file_name = "clip01.svg";
-    let mut f = std::fs::File::create(file_name).unwrap();
-    writeln!(
+    let f = std::fs::File::create(file_name).unwrap();
+    let mut doc = SvgDocument::new(
         f,
-        r#"<svg viewBox="0 0 800 800" xmlns="http://www.w3.org/2000/svg">"#
+        Bounds {
+            min: p2(0.0, 0.0),
+            max: p2(800.0, 800.0),
+        },
+    )?;
+
+    // Each colour goes into its own pen layer.
+    let mut layers = SvgLayers::new();
+    let black = ().svg_layer();
+    layers.add(
+        &black,
+        &PolyLine {
+            ps: vec![p2(0.0, 400.0), p2(800.0, 400.0)],
+            attributes: (),
+            closed: false,
+        },
+    )?;
+    layers.add(
+        &black,
+        &PolyLine {
+            ps: vec![p2(0.0, 0.0), p2(800.0, 0.0)],
+            attributes: (),
+            closed: false,
+        },
+    )?;
+    layers.add(
+        &black,
+        &PolyLine {
+            ps: vec![p2(0.0, 800.0), p2(800.0, 800.0)],
+            attributes: (),
+            closed: false,
+        },
     )?;
-    PolyLine {
-        ps: vec![p2(0.0, 400.0), p2(800.0, 400.0)],
-        attributes: (),
-    }
-    .to_svg(&mut f)?;
-    PolyLine {
-        ps: vec![p2(0.0, 0.0), p2(800.0, 0.0)],
-        attributes: (),
-    }
-    .to_svg(&mut f)?;
-    PolyLine {
-        ps: vec![p2(0.0, 800.0), p2(800.0, 800.0)],
-        attributes: (),
-    }
-    .to_svg(&mut f)?;
 
     let ys = &[0.0f32, 200.0, 600.0, 800.0];
 
     //TODO: Move the properties into the attributes part.
+    let red = StrokeStyle::new(Colour::red());
     PolyLine {
         ps: ys.iter().map(|y| p2(300.0, *y)).collect(),
         attributes: (),
+        closed: false,
     }
     .clip_by(p2(0.0, 1.0), 400.0)
-    .to_svg_with_properties(
-        &mut f,
-        PolyLineProperties {
-            stroke: PolyLineStroke::Red,
-        },
-    )?;
+    .to_svg_with_properties(layers.writer(&red.svg_layer()), &red)?;
+
+    let green = StrokeStyle::new(Colour::green());
     PolyLine {
         ps: ys.iter().map(|y| p2(500.0, 800.0 - *y)).collect(),
         attributes: (),
+        closed: false,
     }
     .clip_by(p2(0.0, 1.0), 400.0)
-    .to_svg_with_properties(
-        &mut f,
-        PolyLineProperties {
-            stroke: PolyLineStroke::Green,
-        },
-    )?;
+    .to_svg_with_properties(layers.writer(&green.svg_layer()), &green)?;
 
-    writeln!(f, "</svg>")?;
+    doc.add(&layers)?;
+    doc.finish()?;
 
     Ok(())
 }
@@ -1,25 +1,25 @@
-use std::sync::{Arc, Mutex};
-
 use gnarled::n2::polyline::PolyLine;
 use gnarled::nbase::bounds::Bounds;
 use gnarled::nbase::line_merger::MegaMerger;
 use gnarled::nbase::line_segment::LineSegment;
 
-use gnarled::svg::SVGable;
+use gnarled::sink::{Sink, SinkError};
+use gnarled::svg::SvgDocument;
 
 use gnarled::n3::Camera;
-use gnarled::svg::{PolyLineProperties, PolyLineStroke};
+use gnarled::style::{Colour, StrokeStyle};
 use rand::{Rng, SeedableRng};
 use rand_pcg::Pcg64Mcg;
 use tokio::sync::mpsc::channel;
 use tokio::sync::mpsc::error::SendError;
-use tokio::task::{JoinError, JoinHandle};
+use tokio::task::JoinError;
 
 #[derive(Debug)]
 pub enum Error {
     JoinError(JoinError),
     SendError,
     IOError(std::io::Error),
+    SinkError(SinkError),
 }
 
 impl<T> From<SendError<T>> for Error {
@@ -40,6 +40,12 @@ impl From<std::io::Error> for Error {
     }
 }
 
+impl From<SinkError> for Error {
+    fn from(e: SinkError) -> Self {
+        Error::SinkError(e)
+    }
+}
+
 fn main() -> Result<(), Error> {
     tokio::runtime::Builder::new_multi_thread()
         .enable_all()
@@ -52,17 +58,17 @@ fn main() -> Result<(), Error> {
 pub async fn async_main() -> Result<(), Error> {
     use gnarled::n3::p3;
     use gnarled::nbase::point::Point;
-    use std::io::Write;
-    use std::ops::DerefMut;
 
     let mut rng: Pcg64Mcg = Pcg64Mcg::seed_from_u64(11);
 
     let file_name = "cube_layer.svg";
     let f = std::fs::File::create(file_name).unwrap();
-    let ff = Arc::new(Mutex::new(f));
-    writeln!(
-        ff.lock().unwrap().deref_mut(),
-        r#"<svg viewBox="0 0 800 800" xmlns="http://www.w3.org/2000/svg">"#
+    let doc = SvgDocument::new(
+        f,
+        Bounds {
+            min: Point::from([0.0, 0.0]),
+            max: Point::from([800.0, 800.0]),
+        },
     )?;
 
     let mut scene = gnarled::n3::Scene::new();
@@ -99,7 +105,7 @@ pub async fn async_main() -> Result<(), Error> {
         }
     }
 
-    {
+    let mut doc = {
         let (sender, recver) = channel(100);
         let (merged_sender, merged_recver) = channel(100);
 
@@ -108,21 +114,15 @@ pub async fn async_main() -> Result<(), Error> {
         let mm = MegaMerger::new(recver, merged_sender);
         let mm = tokio::spawn(async move { mm.run().await });
 
-        let ff = ff.clone();
-        let writer: JoinHandle<Result<(), Error>> = tokio::spawn(async move {
-            let mut recver = merged_recver;
-            while let Some(ls) = recver.recv().await {
-                ls.to_svg(ff.lock().unwrap().deref_mut())?;
-            }
-            Ok(())
-        });
+        let writer = tokio::spawn(Sink::new(merged_recver, doc).run());
         eprintln!("Awaiting writer...");
-        writer.await??;
+        let doc = writer.await??;
         mm.await?.unwrap();
         renderer.await??;
-    }
+        doc
+    };
 
-    PolyLine {
+    doc.add(&PolyLine {
         ps: vec![
             Point::from([0.0, 0.0]),
             Point::from([800.0, 0.0]),
@@ -130,14 +130,11 @@ pub async fn async_main() -> Result<(), Error> {
             Point::from([0.0, 800.0]),
             Point::from([0.0, 0.0]),
         ],
-        attributes: PolyLineProperties {
-            stroke: PolyLineStroke::Red,
-        },
-    }
-    .to_svg(ff.lock().unwrap().deref_mut())
-    .unwrap();
+        attributes: StrokeStyle::new(Colour::red()),
+        closed: false,
+    })?;
 
-    writeln!(ff.lock().unwrap().deref_mut(), "</svg>")?;
+    doc.finish()?;
 
     Ok(())
 }
@@ -1,24 +1,25 @@
-use std::sync::{Arc, Mutex};
-
 use gnarled::n2::polyline::PolyLine;
 use gnarled::nbase::line_merger::MegaMerger;
 use gnarled::nbase::line_segment::LineSegment;
 use gnarled::nbase::point::Point;
+use gnarled::nbase::simplify::{Simplifier, SimplifyOptions};
 
-use gnarled::svg::SVGable;
+use gnarled::sink::{Sink, SinkError};
+use gnarled::svg::SvgDocument;
 
 use gnarled::n3::Camera;
 use gnarled::nbase::bounds::Bounds;
-use gnarled::svg::{PolyLineProperties, PolyLineStroke};
+use gnarled::style::{Colour, StrokeStyle};
 use tokio::sync::mpsc::channel;
 use tokio::sync::mpsc::error::SendError;
-use tokio::task::{JoinError, JoinHandle};
+use tokio::task::JoinError;
 
 #[derive(Debug)]
 pub enum Error {
     JoinError(JoinError),
     SendError,
     IOError(std::io::Error),
+    SinkError(SinkError),
 }
 
 impl<T> From<SendError<T>> for Error {
@@ -39,6 +40,12 @@ impl From<std::io::Error> for Error {
     }
 }
 
+impl From<SinkError> for Error {
+    fn from(e: SinkError) -> Self {
+        Error::SinkError(e)
+    }
+}
+
 fn main() -> Result<(), Error> {
     tokio::runtime::Builder::new_multi_thread()
         .enable_all()
@@ -50,15 +57,15 @@ fn main() -> Result<(), Error> {
 
 pub async fn async_main() -> Result<(), Error> {
     use gnarled::n3::p3;
-    use std::io::Write;
-    use std::ops::DerefMut;
 
     let file_name = "n3_01.svg";
     let f = std::fs::File::create(file_name).unwrap();
-    let ff = Arc::new(Mutex::new(f));
-    writeln!(
-        ff.lock().unwrap().deref_mut(),
-        r#"<svg viewBox="0 0 800 800" xmlns="http://www.w3.org/2000/svg">"#
+    let doc = SvgDocument::new(
+        f,
+        Bounds {
+            min: Point::from([0.0, 0.0]),
+            max: Point::from([800.0, 800.0]),
+        },
     )?;
 
     let mut scene = gnarled::n3::Scene::new();
@@ -102,30 +109,30 @@ pub async fn async_main() -> Result<(), Error> {
         scene.add_primitive(cube);
     }
 
-    {
+    let mut doc = {
         let (sender, recver) = channel(100);
         let (merged_sender, merged_recver) = channel(100);
+        let (simplified_sender, simplified_recver) = channel(100);
 
         let renderer = tokio::spawn(async move { scene.render(&camera, sender).await });
 
         let mm = MegaMerger::new(recver, merged_sender);
         let mm = tokio::spawn(async move { mm.run().await });
 
-        let ff = ff.clone();
-        let writer: JoinHandle<Result<(), Error>> = tokio::spawn(async move {
-            let mut recver = merged_recver;
-            while let Some(ls) = recver.recv().await {
-                ls.to_svg(ff.lock().unwrap().deref_mut())?;
-            }
-            Ok(())
-        });
+        let simplifier =
+            Simplifier::new(merged_recver, simplified_sender, SimplifyOptions::default());
+        let simplifier = tokio::spawn(simplifier.run());
+
+        let writer = tokio::spawn(Sink::new(simplified_recver, doc).run());
         eprintln!("Awaiting writer...");
-        writer.await??;
+        let doc = writer.await??;
+        simplifier.await?.unwrap();
         mm.await?.unwrap();
         renderer.await??;
-    }
+        doc
+    };
 
-    PolyLine {
+    doc.add(&PolyLine {
         ps: vec![
             Point::from([0.0, 0.0]),
             Point::from([800.0, 0.0]),
@@ -133,14 +140,11 @@ pub async fn async_main() -> Result<(), Error> {
             Point::from([0.0, 800.0]),
             Point::from([0.0, 0.0]),
         ],
-        attributes: PolyLineProperties {
-            stroke: PolyLineStroke::Red,
-        },
-    }
-    .to_svg(ff.lock().unwrap().deref_mut())
-    .unwrap();
+        attributes: StrokeStyle::new(Colour::red()),
+        closed: false,
+    })?;
 
-    writeln!(ff.lock().unwrap().deref_mut(), "</svg>")?;
+    doc.finish()?;
 
     Ok(())
 }
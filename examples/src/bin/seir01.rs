@@ -1,7 +1,8 @@
 use gnarled::n2::polyline::PolyLine;
 use gnarled::nbase::point::Point;
 
-use gnarled::svg::SVGable;
+use gnarled::n2::bounds::Bounds;
+use gnarled::svg::SvgDocument;
 
 use gnarled::n2::point::p2;
 
@@ -60,8 +61,9 @@ impl Triangle {
 
 pub fn to_polyline(t: Triangle) -> gnarled::nbase::polyline::PolyLine<2, ()> {
     PolyLine {
-        ps: vec![t.vertices[0], t.vertices[1], t.vertices[2], t.vertices[0]],
+        ps: t.vertices.to_vec(),
         attributes: (),
+        closed: true,
     }
 }
 
@@ -74,13 +76,14 @@ pub fn to_lines(ts: Vec<Triangle>) -> gnarled::nbase::lineset::LineSet<2> {
 }
 
 pub fn main() -> Result<(), std::io::Error> {
-    use std::io::Write;
-
     let file_name = "seir01.svg";
-    let mut f = std::fs::File::create(file_name).unwrap();
-    writeln!(
+    let f = std::fs::File::create(file_name).unwrap();
+    let mut doc = SvgDocument::new(
         f,
-        r#"<svg viewBox="0 0 800 800" xmlns="http://www.w3.org/2000/svg">"#
+        Bounds {
+            min: p2(0.0, 0.0),
+            max: p2(800.0, 800.0),
+        },
     )?;
 
     let c = p2(400., 400.);
@@ -100,9 +103,9 @@ pub fn main() -> Result<(), std::io::Error> {
         ts = ts.into_iter().flat_map(|t| t.sierpinski_refine()).collect();
     }
 
-    to_lines(ts).to_svg(&mut f)?;
+    doc.add(&to_lines(ts))?;
 
-    writeln!(f, "</svg>")?;
+    doc.finish()?;
 
     Ok(())
 }
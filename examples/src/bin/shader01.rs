@@ -1,19 +1,20 @@
 use std::collections::HashMap;
-use std::ops::DerefMut;
-use std::sync::{Arc, Mutex};
 
 use gnarled::nbase::line_segment::LineSegment;
 use gnarled::nbase::polyline::PolyLine;
 
-use gnarled::svg::SVGable;
+use gnarled::sink::{Sink, SinkError};
+use gnarled::svg::{SvgDocument, SvgOptions};
 use tokio::sync::mpsc::{channel, Receiver, Sender};
 
+use gnarled::n2::bounds::Bounds;
 use gnarled::n2::point::p2;
 use gnarled::nbase::line_merger::{
     BinningLineMerger, BinningPolyLineMerger, LineMerger, MegaMerger,
 };
+use gnarled::nbase::path_order::PathOrderer;
 use tokio::sync::mpsc::error::SendError;
-use tokio::task::{JoinError, JoinHandle};
+use tokio::task::JoinError;
 use tokio::try_join;
 
 fn main() -> Result<(), Error> {
@@ -30,6 +31,7 @@ pub enum Error {
     JoinError(JoinError),
     SendError,
     IOError(std::io::Error),
+    SinkError(SinkError),
 }
 
 impl<T> From<SendError<T>> for Error {
@@ -50,18 +52,25 @@ impl From<std::io::Error> for Error {
     }
 }
 
+impl From<SinkError> for Error {
+    fn from(e: SinkError) -> Self {
+        Error::SinkError(e)
+    }
+}
+
 pub async fn async_main() -> Result<(), Error> {
     use gnarled::n2::hl::*;
-    use std::io::Write;
 
     let file_name = "shader01.svg";
     let f = std::fs::File::create(file_name).unwrap();
-    let ff = Arc::new(Mutex::new(f));
-
-    writeln!(
-        ff.lock().unwrap(),
-        r#"<svg viewBox="0 0 800 800" xmlns="http://www.w3.org/2000/svg">"#
+    let mut doc = SvgDocument::new(
+        f,
+        Bounds {
+            min: p2(0.0, 0.0),
+            max: p2(800.0, 800.0),
+        },
     )?;
+    doc.set_options(SvgOptions::compact());
 
     let rand = Box::new(DefaultHasherRandField2D {});
 
@@ -71,7 +80,7 @@ pub async fn async_main() -> Result<(), Error> {
         rand,
     };
 
-    {
+    let mut doc = {
         let circle = Circle {
             center: p2(400.0, 400.0),
             radius: 400.0,
@@ -79,33 +88,42 @@ pub async fn async_main() -> Result<(), Error> {
         };
 
         let (input_a, output_a) = channel(100);
-        let (input_d, mut output_d) = channel(100);
+        let (input_d, output_d) = channel(100);
+        let (input_e, output_e) = channel(100);
 
         let pusher = tokio::spawn(async move { s.apply_async(&circle, input_a).await });
         let mm = MegaMerger::new(output_a, input_d);
 
         let mm = tokio::spawn(async move { mm.run().await });
 
-        let ff = ff.clone();
-        let writer: JoinHandle<Result<(), Error>> = tokio::spawn(async move {
-            while let Some(ls) = output_d.recv().await {
-                ls.to_svg(ff.lock().unwrap().deref_mut())?;
-            }
-            Ok(())
-        });
+        // Reorder the merged lines so the plotter isn't wandering all over the page.
+        let orderer = PathOrderer::new(output_d, input_e, p2(0.0, 0.0), Default::default());
+        let orderer = tokio::spawn(async move { orderer.run().await });
+
+        let writer = tokio::spawn(Sink::new(output_e, doc).run());
         pusher.await?;
         mm.await?.unwrap();
-        writer.await??;
-    }
+        let report = orderer.await?.unwrap();
+        eprintln!(
+            "Pen-up travel reduced from {:.0} to {:.0}",
+            report.travel_before, report.travel_after
+        );
+        let doc = writer.await??;
+        doc
+    };
 
     let ps = (0..101)
         .map(|i| (i as f32) * 2.0f32 * std::f32::consts::PI / 100.0f32)
         .map(|t| p2((400.0 * t.cos()) + 400.0, (400.0 * t.sin()) + 400.0))
         .collect::<Vec<_>>();
-    let p = PolyLine { ps, attributes: () };
-    p.to_svg(ff.lock().unwrap().deref_mut())?;
+    let p = PolyLine {
+        ps,
+        attributes: (),
+        closed: false,
+    };
+    doc.add(&p)?;
 
-    writeln!(ff.lock().unwrap().deref_mut(), "</svg>")?;
+    doc.finish()?;
 
     Ok(())
 }
@@ -1,27 +1,35 @@
 use gnarled::{
-    n2::{lineset::LineSet, point::p2, polyline::PolyLine, tile::make_tile, traits::Rotatable},
+    n2::{
+        bounds::Bounds, lineset::LineSet, point::p2, polyline::PolyLine, tile::make_tile,
+        traits::Rotatable,
+    },
     nbase::traits::{Boundable, Shiftable},
-    svg::SVGable,
+    svg::SvgDocument,
 };
 
 #[allow(clippy::many_single_char_names)]
 fn main() -> Result<(), std::io::Error> {
-    use std::io::Write;
     let file_name = "spiral.svg";
-    let mut f = std::fs::File::create(file_name).unwrap();
+    let f = std::fs::File::create(file_name).unwrap();
     let ps = (0..100)
         .map(|i| (i as f32) * 4.0f32 * std::f32::consts::PI / 100.0f32)
         .map(|t| p2((t * t.cos()) * 40.0, (t * t.sin()) * 40.0))
         .collect::<Vec<_>>();
 
-    writeln!(
+    let mut doc = SvgDocument::new(
         f,
-        r#"<svg viewBox="0 0 800 800" xmlns="http://www.w3.org/2000/svg">"#
+        Bounds {
+            min: p2(0.0, 0.0),
+            max: p2(800.0, 800.0),
+        },
     )?;
-    
 
     // Single spiral arm of the swirl
-    let p = PolyLine { ps, attributes: () };
+    let p = PolyLine {
+        ps,
+        attributes: (),
+        closed: false,
+    };
     let _bb = p.bounds().unwrap();
     let p = p.shift_by(p2(400., 400.));
 
@@ -50,9 +58,9 @@ fn main() -> Result<(), std::io::Error> {
                 (false, false) => spiral_tile.flip_xy(),
             };
             let z = t.place_at(p2(x, y), output_width, output_width);
-            z.to_svg(&mut f)?;
+            doc.add(&z)?;
         }
     }
-    writeln!(f, "</svg>")?;
+    doc.finish()?;
     Ok(())
 }
@@ -1,7 +1,15 @@
+use crate::dxf::DxfLayer;
+use crate::style::StrokeStyle;
+use crate::svg::SvgLayer;
+
 pub trait AttributeReverse {
     fn reverse(&self) -> Self;
 }
 
+// The attributes for the part of a line from parameter `t0` to `t1`,
+// where the line runs from 0 to 1. For a closed line `t1` may be less
+// than `t0`, meaning the part runs from `t0` round through the start to
+// `t1`.
 pub trait AttributeRange {
     fn range(&self, t0: f32, t1: f32) -> Self;
 }
@@ -12,8 +20,24 @@ impl AttributeReverse for () {
     }
 }
 
+// How a line should be drawn.
 pub trait AttributeSVGLine {
-    fn line_attributes(&self) -> String;
+    fn stroke_style(&self) -> StrokeStyle;
+}
+
+// Which layer (pen) a line should be drawn with.
+pub trait AttributeSVGLayer {
+    fn svg_layer(&self) -> SvgLayer;
+}
+
+// Which pen an HPGL plotter should draw a line with.
+pub trait AttributeHPGLPen {
+    fn hpgl_pen(&self) -> u32;
+}
+
+// Which layer a line should be on in a DXF file.
+pub trait AttributeDXFLayer {
+    fn dxf_layer(&self) -> DxfLayer;
 }
 
 impl AttributeRange for () {
@@ -23,7 +47,25 @@ impl AttributeRange for () {
 }
 
 impl AttributeSVGLine for () {
-    fn line_attributes(&self) -> String {
-        return "stroke=\"black\"".to_string();
+    fn stroke_style(&self) -> StrokeStyle {
+        StrokeStyle::default()
+    }
+}
+
+impl AttributeSVGLayer for () {
+    fn svg_layer(&self) -> SvgLayer {
+        SvgLayer::new(1, "black")
+    }
+}
+
+impl AttributeHPGLPen for () {
+    fn hpgl_pen(&self) -> u32 {
+        1
+    }
+}
+
+impl AttributeDXFLayer for () {
+    fn dxf_layer(&self) -> DxfLayer {
+        DxfLayer::new("0", 7)
     }
 }
@@ -0,0 +1,381 @@
+use std::collections::BTreeMap;
+
+use crate::{
+    attributes::AttributeDXFLayer,
+    n2::{cubic_bezier::CubicBezierPath, lineset::LineSet, point::Point},
+    nbase::{line_segment::LineSegment, polyline::PolyLine},
+};
+
+pub trait DXFable {
+    fn to_dxf<W>(&self, w: &mut DxfWriter<W>) -> Result<(), std::io::Error>
+    where
+        W: std::io::Write;
+}
+
+// A DXF layer. `colour` is an AutoCAD Colour Index, e.g. 1 is red and
+// 7 is black (or white, depending on the background).
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct DxfLayer {
+    pub name: String,
+    pub colour: i16,
+}
+
+impl DxfLayer {
+    // Characters R12 doesn't allow in layer names are replaced with '_'.
+    pub fn new(name: &str, colour: i16) -> DxfLayer {
+        let name = name
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '$' {
+                    c.to_ascii_uppercase()
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        DxfLayer { name, colour }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct DxfOptions {
+    // Drawing units (usually mm) per gnarled unit.
+    pub scale: f32,
+    // The point (in gnarled units) that maps to the DXF origin.
+    pub origin: Point,
+    // SVG has y increasing down the page, DXF has it increasing up.
+    pub flip_y: bool,
+    // The height (in gnarled units) of the page or view box y is flipped
+    // within, so that its top edge at origin.y maps to this far up. The
+    // default is an A4 portrait page in millimeters.
+    pub height: f32,
+    // How far (in gnarled units) the lines a bezier is drawn with may
+    // stray from the curve.
+    pub bezier_tolerance: f32,
+}
+
+impl Default for DxfOptions {
+    fn default() -> Self {
+        DxfOptions {
+            scale: 1.0,
+            origin: Point::zero(),
+            flip_y: true,
+            height: 297.0,
+            bezier_tolerance: 0.01,
+        }
+    }
+}
+
+// Writes a group code and its value.
+fn group<O, V>(out: &mut O, code: u16, value: V) -> Result<(), std::io::Error>
+where
+    O: std::io::Write,
+    V: std::fmt::Display,
+{
+    write!(out, "{:>3}\n{}\n", code, value)
+}
+
+// Writes an AutoCAD R12 ASCII DXF file to `w`.
+//
+// The layer table has to come before the entities that use it, so entities
+// are held in memory and the whole file is written by `finish` or on drop.
+pub struct DxfWriter<W>
+where
+    W: std::io::Write,
+{
+    w: W,
+    options: DxfOptions,
+    layers: BTreeMap<String, i16>,
+    entities: Vec<u8>,
+    finished: bool,
+}
+
+impl<W> DxfWriter<W>
+where
+    W: std::io::Write,
+{
+    pub fn new(w: W, options: DxfOptions) -> DxfWriter<W> {
+        DxfWriter {
+            w,
+            options,
+            layers: BTreeMap::new(),
+            entities: vec![],
+            finished: false,
+        }
+    }
+
+    pub fn add<T>(&mut self, item: &T) -> Result<(), std::io::Error>
+    where
+        T: DXFable,
+    {
+        if self.finished {
+            return Err(std::io::Error::other(
+                "DXF output has already been finished",
+            ));
+        }
+        item.to_dxf(self)
+    }
+
+    fn to_drawing(&self, p: Point) -> (f32, f32) {
+        let d = (p - self.options.origin) * self.options.scale;
+        let y = if self.options.flip_y {
+            self.options.height * self.options.scale - d.vs[1]
+        } else {
+            d.vs[1]
+        };
+        (d.vs[0], y)
+    }
+
+    // Writes the points as a 2D POLYLINE on `layer`, which if `closed`
+    // joins its last point back to the first.
+    pub fn polyline(
+        &mut self,
+        layer: &DxfLayer,
+        ps: &[Point],
+        closed: bool,
+    ) -> Result<(), std::io::Error> {
+        if ps.len() <= 1 {
+            return Ok(());
+        }
+        self.layers
+            .entry(layer.name.clone())
+            .or_insert(layer.colour);
+        let e = &mut self.entities;
+        group(e, 0, "POLYLINE")?;
+        group(e, 8, &layer.name)?;
+        // Vertices follow
+        group(e, 66, 1)?;
+        group(e, 10, 0.0)?;
+        group(e, 20, 0.0)?;
+        group(e, 30, 0.0)?;
+        group(e, 70, if closed { 1 } else { 0 })?;
+        for p in ps {
+            let (x, y) = self.to_drawing(*p);
+            let e = &mut self.entities;
+            group(e, 0, "VERTEX")?;
+            group(e, 8, &layer.name)?;
+            group(e, 10, x)?;
+            group(e, 20, y)?;
+            group(e, 30, 0.0)?;
+        }
+        let e = &mut self.entities;
+        group(e, 0, "SEQEND")?;
+        group(e, 8, &layer.name)
+    }
+
+    // The layers refer to the CONTINUOUS line type, which has to be defined
+    // first for readers that check.
+    fn write_tables(&mut self) -> Result<(), std::io::Error> {
+        let w = &mut self.w;
+        group(w, 0, "SECTION")?;
+        group(w, 2, "TABLES")?;
+        group(w, 0, "TABLE")?;
+        group(w, 2, "LTYPE")?;
+        group(w, 70, 1)?;
+        group(w, 0, "LTYPE")?;
+        group(w, 2, "CONTINUOUS")?;
+        group(w, 70, 0)?;
+        group(w, 3, "Solid line")?;
+        // Alignment, number of dashes and pattern length.
+        group(w, 72, 65)?;
+        group(w, 73, 0)?;
+        group(w, 40, 0.0)?;
+        group(w, 0, "ENDTAB")?;
+        group(w, 0, "TABLE")?;
+        group(w, 2, "LAYER")?;
+        group(w, 70, self.layers.len())?;
+        for (name, colour) in &self.layers {
+            group(w, 0, "LAYER")?;
+            group(w, 2, name)?;
+            group(w, 70, 0)?;
+            group(w, 62, colour)?;
+            group(w, 6, "CONTINUOUS")?;
+        }
+        group(w, 0, "ENDTAB")?;
+        group(w, 0, "ENDSEC")
+    }
+
+    // Writes the file. Calling this more than once is harmless.
+    pub fn finish(&mut self) -> Result<(), std::io::Error> {
+        if self.finished {
+            return Ok(());
+        }
+        write!(
+            self.w,
+            "  0\nSECTION\n  2\nHEADER\n  9\n$ACADVER\n  1\nAC1009\n  0\nENDSEC\n"
+        )?;
+        self.write_tables()?;
+        write!(self.w, "  0\nSECTION\n  2\nENTITIES\n")?;
+        self.w.write_all(&self.entities)?;
+        write!(self.w, "  0\nENDSEC\n  0\nEOF\n")?;
+        self.w.flush()?;
+        // Only now, so that a failed finish is retried on drop rather than
+        // losing the buffered entities.
+        self.finished = true;
+        Ok(())
+    }
+}
+
+impl<W> Drop for DxfWriter<W>
+where
+    W: std::io::Write,
+{
+    fn drop(&mut self) {
+        // Errors can't be reported from drop, call finish to see them.
+        let _ = self.finish();
+    }
+}
+
+impl<A> DXFable for PolyLine<2, A>
+where
+    A: AttributeDXFLayer,
+{
+    fn to_dxf<W>(&self, w: &mut DxfWriter<W>) -> Result<(), std::io::Error>
+    where
+        W: std::io::Write,
+    {
+        w.polyline(&self.attributes.dxf_layer(), &self.ps, self.closed)
+    }
+}
+
+impl<A> DXFable for LineSegment<2, A>
+where
+    A: AttributeDXFLayer,
+{
+    fn to_dxf<W>(&self, w: &mut DxfWriter<W>) -> Result<(), std::io::Error>
+    where
+        W: std::io::Write,
+    {
+        w.polyline(&self.attributes.dxf_layer(), &self.ps, false)
+    }
+}
+
+impl DXFable for LineSet {
+    fn to_dxf<W>(&self, w: &mut DxfWriter<W>) -> Result<(), std::io::Error>
+    where
+        W: std::io::Write,
+    {
+        for l in &self.lines {
+            l.to_dxf(w)?;
+        }
+        Ok(())
+    }
+}
+
+// R12 has no splines, so curves are flattened into polylines.
+impl DXFable for CubicBezierPath {
+    fn to_dxf<W>(&self, w: &mut DxfWriter<W>) -> Result<(), std::io::Error>
+    where
+        W: std::io::Write,
+    {
+        if self.ps.len() <= 1 {
+            return Ok(());
+        }
+        let pl = self.flatten(w.options.bezier_tolerance);
+        w.polyline(&().dxf_layer(), &pl.ps, pl.closed)
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::n2::point::p2;
+    use crate::style::{Colour, StrokeStyle};
+
+    #[test]
+    pub fn polyline_to_dxf() {
+        let mut out = vec![];
+        {
+            let mut w = DxfWriter::new(&mut out, DxfOptions::default());
+            w.add(&PolyLine {
+                ps: vec![p2(0.0, 0.0), p2(10.0, 5.0)],
+                attributes: StrokeStyle::new(Colour::red()),
+                closed: false,
+            })
+            .unwrap();
+        }
+        let out = String::from_utf8(out).unwrap();
+        let codes: Vec<&str> = out.lines().collect();
+        assert!(out.contains("  0\nLAYER\n  2\nRED\n 70\n0\n 62\n1\n"));
+        assert_eq!(codes.iter().filter(|l| **l == "VERTEX").count(), 2);
+        // Flipped within the page, which is A4 by default.
+        assert!(out.contains(" 10\n10\n 20\n292\n"));
+        let (ltype, layer) = (
+            out.find("  0\nLTYPE\n  2\nCONTINUOUS\n").unwrap(),
+            out.find("  0\nTABLE\n  2\nLAYER\n").unwrap(),
+        );
+        assert!(ltype < layer);
+
+        // The corners of the page map to the corners of the drawing.
+        let options = DxfOptions {
+            origin: p2(10.0, 20.0),
+            height: 100.0,
+            scale: 2.0,
+            ..Default::default()
+        };
+        let w = DxfWriter::new(vec![], options);
+        assert_eq!(w.to_drawing(p2(10.0, 120.0)), (0.0, 0.0));
+        assert_eq!(w.to_drawing(p2(60.0, 20.0)), (100.0, 200.0));
+        assert!(out.ends_with("  0\nEOF\n"));
+    }
+
+    // Fails every write until it is allowed to succeed.
+    struct Failing<'a>(&'a std::cell::Cell<bool>, Vec<u8>);
+
+    impl std::io::Write for Failing<'_> {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            if self.0.get() {
+                self.1.write(buf)
+            } else {
+                Err(std::io::Error::other("not yet"))
+            }
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    pub fn failed_finish_keeps_entities() {
+        let ok = std::cell::Cell::new(false);
+        let mut w = DxfWriter::new(Failing(&ok, vec![]), DxfOptions::default());
+        w.add(&PolyLine::new(vec![p2(0.0, 0.0), p2(10.0, 5.0)], ()))
+            .unwrap();
+        assert!(w.finish().is_err());
+        ok.set(true);
+        w.finish().unwrap();
+        let out = String::from_utf8(w.w.1.clone()).unwrap();
+        assert_eq!(out.lines().filter(|l| *l == "VERTEX").count(), 2);
+        assert!(out.ends_with("  0\nEOF\n"));
+    }
+
+    #[test]
+    pub fn malformed_bezier_to_dxf() {
+        // A quarter circle with a stray point, flattened with a negative
+        // tolerance.
+        let k = 10.0 * 0.5523;
+        let path = CubicBezierPath {
+            ps: vec![
+                p2(10.0, 0.0),
+                p2(10.0, k),
+                p2(k, 10.0),
+                p2(0.0, 10.0),
+                p2(5.0, 5.0),
+            ],
+        };
+        let options = DxfOptions {
+            bezier_tolerance: -1.0,
+            ..Default::default()
+        };
+        let mut out = vec![];
+        {
+            let mut w = DxfWriter::new(&mut out, options);
+            w.add(&path).unwrap();
+        }
+        let out = String::from_utf8(out).unwrap();
+        let vertices = out.lines().filter(|l| *l == "VERTEX").count();
+        assert!(vertices > 100 && vertices <= 1025);
+        assert!(!out.contains(" 10\n5\n"));
+    }
+}
@@ -0,0 +1,373 @@
+use crate::{
+    n2::{
+        lineset::LineSet,
+        point::{p2, Point},
+    },
+    nbase::{
+        line_segment::LineSegment, plot_stats::PlotterModel, point::distance, polyline::PolyLine,
+    },
+};
+
+// Commands for the EiBotBoard, as used in the AxiDraw.
+pub trait EBBable {
+    fn to_ebb<W>(&self, w: &mut EbbWriter<W>) -> Result<(), std::io::Error>
+    where
+        W: std::io::Write;
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct EbbOptions {
+    // Speeds, acceleration and pen timing, in mm and seconds.
+    pub model: PlotterModel,
+    // Motor steps per mm of carriage travel. 2032 steps per inch
+    // at 1/16 microstepping on the AxiDraw.
+    pub steps_per_mm: f32,
+    // mm per gnarled unit.
+    pub scale: f32,
+    // The point (in gnarled units) that maps to the home position.
+    pub origin: Point,
+    // Servo positions for the pen, sent with SC,4 / SC,5 if set.
+    pub pen_up_position: Option<u16>,
+    pub pen_down_position: Option<u16>,
+    // Length of each SM move in ms. Moves are chopped into slices of this
+    // length to approximate the acceleration profile.
+    pub time_slice: u32,
+}
+
+impl Default for EbbOptions {
+    fn default() -> Self {
+        EbbOptions {
+            model: PlotterModel::default(),
+            steps_per_mm: 2032.0 / 25.4,
+            scale: 1.0,
+            origin: Point::zero(),
+            pen_up_position: None,
+            pen_down_position: None,
+            time_slice: 25,
+        }
+    }
+}
+
+// Writes EBB commands to `w`, usually a serial port.
+//
+// The motors are enabled and the pen raised on creation. `finish` (or drop)
+// raises the pen, returns to the home position and turns the motors off.
+// Replies from the board are not read.
+pub struct EbbWriter<W>
+where
+    W: std::io::Write,
+{
+    w: W,
+    options: EbbOptions,
+    // Current carriage position, in motor steps from home.
+    steps: (i64, i64),
+    pen_down: bool,
+    finished: bool,
+}
+
+impl<W> EbbWriter<W>
+where
+    W: std::io::Write,
+{
+    pub fn new(w: W, options: EbbOptions) -> Result<EbbWriter<W>, std::io::Error> {
+        let mut result = EbbWriter {
+            w,
+            options,
+            steps: (0, 0),
+            pen_down: true,
+            finished: false,
+        };
+        // Both motors on, 1/16 microstepping.
+        result.command("EM,1,1")?;
+        if let Some(p) = options.pen_up_position {
+            result.command(&format!("SC,4,{}", p))?;
+        }
+        if let Some(p) = options.pen_down_position {
+            result.command(&format!("SC,5,{}", p))?;
+        }
+        result.pen_up()?;
+        Ok(result)
+    }
+
+    pub fn add<T>(&mut self, item: &T) -> Result<(), std::io::Error>
+    where
+        T: EBBable,
+    {
+        if self.finished {
+            return Err(std::io::Error::other(
+                "EBB output has already been finished",
+            ));
+        }
+        item.to_ebb(self)
+    }
+
+    fn command(&mut self, c: &str) -> Result<(), std::io::Error> {
+        write!(self.w, "{}\r", c)
+    }
+
+    // Position in mm from home.
+    fn to_machine(&self, p: Point) -> Point {
+        (p - self.options.origin) * self.options.scale
+    }
+
+    // Current position in mm from home.
+    fn position(&self) -> Point {
+        p2(
+            self.steps.0 as f32 / self.options.steps_per_mm,
+            self.steps.1 as f32 / self.options.steps_per_mm,
+        )
+    }
+
+    fn pen_delay(&self) -> u32 {
+        (self.options.model.pen_lift_time * 1000.0).round() as u32
+    }
+
+    pub fn pen_up(&mut self) -> Result<(), std::io::Error> {
+        if !self.pen_down {
+            return Ok(());
+        }
+        self.command(&format!("SP,1,{}", self.pen_delay()))?;
+        self.pen_down = false;
+        Ok(())
+    }
+
+    pub fn pen_down(&mut self) -> Result<(), std::io::Error> {
+        if self.pen_down {
+            return Ok(());
+        }
+        self.command(&format!("SP,0,{}", self.pen_delay()))?;
+        self.pen_down = true;
+        Ok(())
+    }
+
+    // Moves the carriage to `target` (mm from home), in SM slices.
+    fn step_to(&mut self, target: Point, ms: u32) -> Result<(), std::io::Error> {
+        let x = (target.vs[0] * self.options.steps_per_mm).round() as i64;
+        let y = (target.vs[1] * self.options.steps_per_mm).round() as i64;
+        let (dx, dy) = (x - self.steps.0, y - self.steps.1);
+        if ms == 0 && dx == 0 && dy == 0 {
+            return Ok(());
+        }
+        self.steps = (x, y);
+        let ms = ms.max(1);
+        // The AxiDraw is CoreXY-like: each motor moves both axes.
+        self.command(&format!("SM,{},{},{}", ms, dx + dy, dx - dy))
+    }
+
+    // Moves along the path `ps` (mm from home) from rest to rest, following a
+    // trapezoidal velocity profile along its length.
+    fn run(&mut self, ps: &[Point], max_speed: f32) -> Result<(), std::io::Error> {
+        let lengths: Vec<f32> = ps.windows(2).map(|w| distance(w[0], w[1])).collect();
+        let total: f32 = lengths.iter().sum();
+        if total <= 0.0 {
+            return Ok(());
+        }
+        let a = self.options.model.acceleration;
+        // Peak speed, lower than max_speed if the run is too short to reach it.
+        let v = max_speed.min((total * a).sqrt());
+        let ramp_time = v / a;
+        let ramp_length = 0.5 * v * ramp_time;
+        let duration = 2.0 * ramp_time + (total - 2.0 * ramp_length) / v;
+        // Distance travelled after time t.
+        let s = |t: f32| {
+            if t < ramp_time {
+                0.5 * a * t * t
+            } else if t < duration - ramp_time {
+                ramp_length + v * (t - ramp_time)
+            } else {
+                let r = duration - t;
+                total - 0.5 * a * r * r
+            }
+        };
+
+        let slice = self.options.time_slice.max(1) as f32 / 1000.0;
+        let slices = (duration / slice).ceil() as usize;
+        let mut segment = 0;
+        let mut segment_start = 0.0;
+        let mut elapsed_ms = 0;
+        for i in 1..=slices {
+            let t = (i as f32 * slice).min(duration);
+            let d = if i == slices { total } else { s(t) };
+            while segment + 1 < lengths.len() && segment_start + lengths[segment] < d {
+                segment_start += lengths[segment];
+                segment += 1;
+            }
+            let alpha = if lengths[segment] > 0.0 {
+                ((d - segment_start) / lengths[segment]).clamp(0.0, 1.0)
+            } else {
+                1.0
+            };
+            let p = Point::lerp(alpha, ps[segment], ps[segment + 1]);
+            let ms = (t * 1000.0).round() as u32;
+            self.step_to(p, ms - elapsed_ms)?;
+            elapsed_ms = ms;
+        }
+        Ok(())
+    }
+
+    // Travels to the first point with the pen up, then draws through the rest.
+    pub fn stroke(&mut self, ps: &[Point]) -> Result<(), std::io::Error> {
+        if ps.len() <= 1 {
+            return Ok(());
+        }
+        let ps: Vec<Point> = ps.iter().map(|p| self.to_machine(*p)).collect();
+        let current = self.position();
+        self.pen_up()?;
+        self.run(&[current, ps[0]], self.options.model.max_travel_speed)?;
+        self.pen_down()?;
+
+        // Stop only at corners sharper than the cornering angle.
+        let cos_corner = self.options.model.cornering_angle.cos();
+        let mut start = 0;
+        for i in 1..ps.len() - 1 {
+            let u = ps[i] - ps[i - 1];
+            let v = ps[i + 1] - ps[i];
+            let uv = u.norm_squared().sqrt() * v.norm_squared().sqrt();
+            if uv > 0.0 && u.dot(v) >= cos_corner * uv {
+                continue;
+            }
+            self.run(&ps[start..=i], self.options.model.max_draw_speed)?;
+            start = i;
+        }
+        self.run(&ps[start..], self.options.model.max_draw_speed)?;
+        self.pen_up()
+    }
+
+    // Raises the pen, returns home and turns the motors off.
+    // Calling this more than once is harmless.
+    pub fn finish(&mut self) -> Result<(), std::io::Error> {
+        if self.finished {
+            return Ok(());
+        }
+        self.pen_up()?;
+        let current = self.position();
+        self.run(
+            &[current, Point::zero()],
+            self.options.model.max_travel_speed,
+        )?;
+        self.command("EM,0,0")?;
+        self.w.flush()?;
+        // Only now, so that a failed finish is retried on drop and the
+        // pen isn't left down with the motors on.
+        self.finished = true;
+        Ok(())
+    }
+}
+
+impl<W> Drop for EbbWriter<W>
+where
+    W: std::io::Write,
+{
+    fn drop(&mut self) {
+        // Errors can't be reported from drop, call finish to see them.
+        let _ = self.finish();
+    }
+}
+
+impl<A> EBBable for PolyLine<2, A> {
+    fn to_ebb<W>(&self, w: &mut EbbWriter<W>) -> Result<(), std::io::Error>
+    where
+        W: std::io::Write,
+    {
+        w.stroke(&self.path_points())
+    }
+}
+
+impl<A> EBBable for LineSegment<2, A> {
+    fn to_ebb<W>(&self, w: &mut EbbWriter<W>) -> Result<(), std::io::Error>
+    where
+        W: std::io::Write,
+    {
+        w.stroke(&self.ps)
+    }
+}
+
+impl EBBable for LineSet {
+    fn to_ebb<W>(&self, w: &mut EbbWriter<W>) -> Result<(), std::io::Error>
+    where
+        W: std::io::Write,
+    {
+        for l in &self.lines {
+            l.to_ebb(w)?;
+        }
+        Ok(())
+    }
+}
+
+// Stands in for the plotter's serial port, recording each command sent.
+#[derive(Clone, Debug, Default)]
+pub struct MockSerialPort {
+    pub commands: Vec<String>,
+    partial: Vec<u8>,
+}
+
+impl MockSerialPort {
+    pub fn new() -> MockSerialPort {
+        MockSerialPort::default()
+    }
+}
+
+impl std::io::Write for MockSerialPort {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        for &b in buf {
+            if b == b'\r' {
+                let c = String::from_utf8_lossy(&self.partial).into_owned();
+                self.commands.push(c);
+                self.partial.clear();
+            } else {
+                self.partial.push(b);
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    #[test]
+    pub fn square_returns_home() {
+        let mut port = MockSerialPort::new();
+        {
+            let mut w = EbbWriter::new(&mut port, EbbOptions::default()).unwrap();
+            w.add(&PolyLine {
+                ps: vec![
+                    p2(10.0, 10.0),
+                    p2(20.0, 10.0),
+                    p2(20.0, 20.0),
+                    p2(10.0, 20.0),
+                    p2(10.0, 10.0),
+                ],
+                attributes: (),
+                closed: false,
+            })
+            .unwrap();
+        }
+        let c = &port.commands;
+        assert_eq!(c.first().unwrap(), "EM,1,1");
+        assert_eq!(c.last().unwrap(), "EM,0,0");
+        let pen: Vec<&str> = c
+            .iter()
+            .filter(|c| c.starts_with("SP"))
+            .map(|c| c.as_str())
+            .collect();
+        assert_eq!(pen, vec!["SP,1,200", "SP,0,200", "SP,1,200"]);
+
+        // Motor steps sum to zero, and stay within the board's step rate.
+        let (mut a, mut b) = (0, 0);
+        for c in c.iter().filter(|c| c.starts_with("SM")) {
+            let f: Vec<i64> = c[3..].split(',').map(|s| s.parse().unwrap()).collect();
+            assert!(f[0] > 0);
+            assert!(f[1].abs() * 1000 / f[0] <= 25000);
+            assert!(f[2].abs() * 1000 / f[0] <= 25000);
+            a += f[1];
+            b += f[2];
+        }
+        assert_eq!((a, b), (0, 0));
+    }
+}
@@ -0,0 +1,325 @@
+use crate::{
+    n2::{lineset::LineSet, point::Point},
+    nbase::{line_segment::LineSegment, polyline::PolyLine},
+    svg::PageUnit,
+};
+
+pub trait GCodeable {
+    fn to_gcode<W>(&self, w: &mut GcodeWriter<W>) -> Result<(), std::io::Error>
+    where
+        W: std::io::Write;
+}
+
+// How the machine raises and lowers the pen.
+#[derive(Clone, Debug)]
+pub enum PenLift {
+    // Move the Z axis to `up` or `down`, lowering at `feed`.
+    ZAxis { up: f32, down: f32, feed: f32 },
+    // Arbitrary commands, e.g. servo M-codes like "M3 S30" / "M5".
+    Commands { up: String, down: String },
+}
+
+#[derive(Clone, Debug)]
+pub struct GcodeOptions {
+    pub pen: PenLift,
+    // Feed rates in machine units per minute.
+    pub draw_feed: f32,
+    pub travel_feed: f32,
+    // Seconds to wait after lowering the pen, to let it settle.
+    pub dwell_after_down: f32,
+    pub units: PageUnit,
+    // Machine units per gnarled unit.
+    pub scale: f32,
+    // The point (in gnarled units) that maps to the machine origin.
+    pub origin: Point,
+    // SVG has y increasing down the page, most machines have it increasing up.
+    pub flip_y: bool,
+    // The height (in gnarled units) of the page or view box y is flipped
+    // within, so that its top edge at origin.y maps to this far up. The
+    // default is an A4 portrait page in millimeters.
+    pub height: f32,
+}
+
+impl Default for GcodeOptions {
+    fn default() -> Self {
+        GcodeOptions {
+            pen: PenLift::ZAxis {
+                up: 5.0,
+                down: 0.0,
+                feed: 500.0,
+            },
+            draw_feed: 1000.0,
+            travel_feed: 3000.0,
+            dwell_after_down: 0.1,
+            units: PageUnit::Millimeters,
+            scale: 1.0,
+            origin: Point::zero(),
+            flip_y: true,
+            height: 297.0,
+        }
+    }
+}
+
+// Writes G-code for GRBL style pen plotters to `w`.
+//
+// The preamble is written on creation, and the pen is lifted and
+// the program ended by `finish` or on drop.
+pub struct GcodeWriter<W>
+where
+    W: std::io::Write,
+{
+    w: W,
+    options: GcodeOptions,
+    pen_down: bool,
+    finished: bool,
+}
+
+impl<W> GcodeWriter<W>
+where
+    W: std::io::Write,
+{
+    pub fn new(mut w: W, options: GcodeOptions) -> Result<GcodeWriter<W>, std::io::Error> {
+        match options.units {
+            PageUnit::Millimeters => writeln!(w, "G21")?,
+            PageUnit::Inches => writeln!(w, "G20")?,
+        }
+        // Absolute positioning
+        writeln!(w, "G90")?;
+        let mut result = GcodeWriter {
+            w,
+            options,
+            pen_down: true,
+            finished: false,
+        };
+        result.pen_up()?;
+        Ok(result)
+    }
+
+    pub fn add<T>(&mut self, item: &T) -> Result<(), std::io::Error>
+    where
+        T: GCodeable,
+    {
+        if self.finished {
+            return Err(std::io::Error::other(
+                "G-code output has already been finished",
+            ));
+        }
+        item.to_gcode(self)
+    }
+
+    fn to_machine(&self, p: Point) -> (f32, f32) {
+        let d = (p - self.options.origin) * self.options.scale;
+        let y = if self.options.flip_y {
+            self.options.height * self.options.scale - d.vs[1]
+        } else {
+            d.vs[1]
+        };
+        (d.vs[0], y)
+    }
+
+    pub fn pen_up(&mut self) -> Result<(), std::io::Error> {
+        if !self.pen_down {
+            return Ok(());
+        }
+        match &self.options.pen {
+            PenLift::ZAxis { up, .. } => writeln!(self.w, "G0 Z{:.3}", up)?,
+            PenLift::Commands { up, .. } => writeln!(self.w, "{}", up)?,
+        }
+        self.pen_down = false;
+        Ok(())
+    }
+
+    pub fn pen_down(&mut self) -> Result<(), std::io::Error> {
+        if self.pen_down {
+            return Ok(());
+        }
+        match &self.options.pen {
+            PenLift::ZAxis { down, feed, .. } => writeln!(self.w, "G1 Z{:.3} F{:.0}", down, feed)?,
+            PenLift::Commands { down, .. } => writeln!(self.w, "{}", down)?,
+        }
+        if self.options.dwell_after_down > 0.0 {
+            writeln!(self.w, "G4 P{:.3}", self.options.dwell_after_down)?;
+        }
+        self.pen_down = true;
+        Ok(())
+    }
+
+    // Travels to the first point with the pen up, then draws through the rest.
+    // Travel is a G1 at `travel_feed` rather than a rapid G0, which ignores
+    // the feed rate.
+    pub fn stroke(&mut self, ps: &[Point]) -> Result<(), std::io::Error> {
+        if ps.len() <= 1 {
+            return Ok(());
+        }
+        self.pen_up()?;
+        let (x, y) = self.to_machine(ps[0]);
+        writeln!(
+            self.w,
+            "G1 X{:.3} Y{:.3} F{:.0}",
+            x, y, self.options.travel_feed
+        )?;
+        self.pen_down()?;
+        for (i, p) in ps[1..].iter().enumerate() {
+            let (x, y) = self.to_machine(*p);
+            if i == 0 {
+                writeln!(
+                    self.w,
+                    "G1 X{:.3} Y{:.3} F{:.0}",
+                    x, y, self.options.draw_feed
+                )?;
+            } else {
+                writeln!(self.w, "G1 X{:.3} Y{:.3}", x, y)?;
+            }
+        }
+        self.pen_up()
+    }
+
+    // Lifts the pen and ends the program. Calling this more than once is harmless.
+    pub fn finish(&mut self) -> Result<(), std::io::Error> {
+        if self.finished {
+            return Ok(());
+        }
+        self.pen_up()?;
+        writeln!(self.w, "M2")?;
+        self.w.flush()?;
+        // Only now, so that a failed finish is retried on drop.
+        self.finished = true;
+        Ok(())
+    }
+}
+
+impl<W> Drop for GcodeWriter<W>
+where
+    W: std::io::Write,
+{
+    fn drop(&mut self) {
+        // Errors can't be reported from drop, call finish to see them.
+        let _ = self.finish();
+    }
+}
+
+impl<A> GCodeable for PolyLine<2, A> {
+    fn to_gcode<W>(&self, w: &mut GcodeWriter<W>) -> Result<(), std::io::Error>
+    where
+        W: std::io::Write,
+    {
+        w.stroke(&self.path_points())
+    }
+}
+
+impl<A> GCodeable for LineSegment<2, A> {
+    fn to_gcode<W>(&self, w: &mut GcodeWriter<W>) -> Result<(), std::io::Error>
+    where
+        W: std::io::Write,
+    {
+        w.stroke(&self.ps)
+    }
+}
+
+impl GCodeable for LineSet {
+    fn to_gcode<W>(&self, w: &mut GcodeWriter<W>) -> Result<(), std::io::Error>
+    where
+        W: std::io::Write,
+    {
+        for l in &self.lines {
+            l.to_gcode(w)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::n2::point::p2;
+
+    fn gcode<T>(options: GcodeOptions, items: &[T]) -> String
+    where
+        T: GCodeable,
+    {
+        let mut out = vec![];
+        {
+            let mut w = GcodeWriter::new(&mut out, options).unwrap();
+            for item in items {
+                w.add(item).unwrap();
+            }
+        }
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    pub fn z_axis_pen() {
+        let triangle = PolyLine {
+            ps: vec![p2(0.0, 0.0), p2(4.0, 0.0), p2(4.0, 3.0)],
+            attributes: (),
+            closed: true,
+        };
+        let options = GcodeOptions {
+            height: 10.0,
+            ..Default::default()
+        };
+        assert_eq!(
+            gcode(options, &[triangle]),
+            "G21\nG90\nG0 Z5.000\n\
+            G1 X0.000 Y10.000 F3000\nG1 Z0.000 F500\nG4 P0.100\n\
+            G1 X4.000 Y10.000 F1000\nG1 X4.000 Y7.000\nG1 X0.000 Y10.000\n\
+            G0 Z5.000\nM2\n"
+        );
+    }
+
+    #[test]
+    pub fn servo_pen_in_inches() {
+        let lines = LineSet {
+            lines: vec![
+                PolyLine {
+                    ps: vec![p2(2.0, 2.0), p2(4.0, 2.0)],
+                    attributes: (),
+                    closed: false,
+                },
+                PolyLine {
+                    ps: vec![p2(0.0, 0.0), p2(2.0, 0.0), p2(2.0, 4.0)],
+                    attributes: (),
+                    closed: false,
+                },
+            ],
+        };
+        let options = GcodeOptions {
+            pen: PenLift::Commands {
+                up: "M5".to_string(),
+                down: "M3 S30".to_string(),
+            },
+            draw_feed: 40.0,
+            travel_feed: 100.0,
+            dwell_after_down: 0.0,
+            units: PageUnit::Inches,
+            scale: 0.5,
+            flip_y: false,
+            ..Default::default()
+        };
+        assert_eq!(
+            gcode(options, &[lines]),
+            "G20\nG90\nM5\n\
+            G1 X1.000 Y1.000 F100\nM3 S30\nG1 X2.000 Y1.000 F40\nM5\n\
+            G1 X0.000 Y0.000 F100\nM3 S30\nG1 X1.000 Y0.000 F40\nG1 X1.000 Y2.000\nM5\n\
+            M2\n"
+        );
+    }
+
+    #[test]
+    pub fn page_to_machine() {
+        // The corners of the page map to the corners of the work area.
+        let w = GcodeWriter::new(vec![], GcodeOptions::default()).unwrap();
+        assert_eq!(w.to_machine(p2(0.0, 297.0)), (0.0, 0.0));
+        assert_eq!(w.to_machine(p2(210.0, 0.0)), (210.0, 297.0));
+
+        let options = GcodeOptions {
+            origin: p2(10.0, 20.0),
+            height: 100.0,
+            scale: 2.0,
+            ..Default::default()
+        };
+        let w = GcodeWriter::new(vec![], options).unwrap();
+        assert_eq!(w.to_machine(p2(10.0, 120.0)), (0.0, 0.0));
+        assert_eq!(w.to_machine(p2(60.0, 20.0)), (100.0, 200.0));
+    }
+}
@@ -0,0 +1,265 @@
+use crate::{
+    attributes::AttributeHPGLPen,
+    n2::{cubic_bezier::CubicBezierPath, lineset::LineSet, point::Point},
+    nbase::{line_segment::LineSegment, polyline::PolyLine},
+};
+
+pub trait HPGLable {
+    fn to_hpgl<W>(&self, w: &mut HpglWriter<W>) -> Result<(), std::io::Error>
+    where
+        W: std::io::Write;
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct HpglOptions {
+    // Plotter units per gnarled unit. A plotter unit is 0.025mm,
+    // so the default of 40 treats gnarled units as millimeters.
+    pub scale: f32,
+    // The point (in gnarled units) that maps to the plotter origin.
+    pub origin: Point,
+    // SVG has y increasing down the page, HPGL has it increasing up.
+    pub flip_y: bool,
+    // The height (in gnarled units) of the page or view box y is flipped
+    // within, so that its top edge at origin.y maps to this far up. The
+    // default is an A4 portrait page in millimeters.
+    pub height: f32,
+    // How far (in gnarled units) the lines a bezier is drawn with may
+    // stray from the curve.
+    pub bezier_tolerance: f32,
+}
+
+impl Default for HpglOptions {
+    fn default() -> Self {
+        HpglOptions {
+            scale: 40.0,
+            origin: Point::zero(),
+            flip_y: true,
+            height: 297.0,
+            bezier_tolerance: 0.01,
+        }
+    }
+}
+
+// Writes HPGL commands to `w`.
+//
+// Like `SvgDocument` the initialisation is written on creation, and the
+// trailing pen-up and pen-return are written by `finish` or on drop.
+pub struct HpglWriter<W>
+where
+    W: std::io::Write,
+{
+    w: W,
+    options: HpglOptions,
+    pen: Option<u32>,
+    finished: bool,
+}
+
+impl<W> HpglWriter<W>
+where
+    W: std::io::Write,
+{
+    pub fn new(mut w: W, options: HpglOptions) -> Result<HpglWriter<W>, std::io::Error> {
+        writeln!(w, "IN;")?;
+        Ok(HpglWriter {
+            w,
+            options,
+            pen: None,
+            finished: false,
+        })
+    }
+
+    pub fn add<T>(&mut self, item: &T) -> Result<(), std::io::Error>
+    where
+        T: HPGLable,
+    {
+        if self.finished {
+            return Err(std::io::Error::other(
+                "HPGL output has already been finished",
+            ));
+        }
+        item.to_hpgl(self)
+    }
+
+    fn to_plotter_units(&self, p: Point) -> (i32, i32) {
+        let d = (p - self.options.origin) * self.options.scale;
+        let y = if self.options.flip_y {
+            self.options.height * self.options.scale - d.vs[1]
+        } else {
+            d.vs[1]
+        };
+        (d.vs[0].round() as i32, y.round() as i32)
+    }
+
+    // Selects a pen, only emitting SP if it differs from the current one.
+    pub fn select_pen(&mut self, pen: u32) -> Result<(), std::io::Error> {
+        if self.pen != Some(pen) {
+            writeln!(self.w, "SP{};", pen)?;
+            self.pen = Some(pen);
+        }
+        Ok(())
+    }
+
+    // Moves to the first point with the pen up, then draws through the rest.
+    pub fn stroke(&mut self, ps: &[Point]) -> Result<(), std::io::Error> {
+        if ps.len() <= 1 {
+            return Ok(());
+        }
+        let (x, y) = self.to_plotter_units(ps[0]);
+        writeln!(self.w, "PU{},{};", x, y)?;
+        let coords = ps[1..]
+            .iter()
+            .map(|p| {
+                let (x, y) = self.to_plotter_units(*p);
+                format!("{},{}", x, y)
+            })
+            .collect::<Vec<_>>();
+        writeln!(self.w, "PD{};", coords.join(","))?;
+        Ok(())
+    }
+
+    // Lifts the pen and puts it away. Calling this more than once is harmless.
+    pub fn finish(&mut self) -> Result<(), std::io::Error> {
+        if self.finished {
+            return Ok(());
+        }
+        writeln!(self.w, "PU;")?;
+        writeln!(self.w, "SP0;")?;
+        self.w.flush()?;
+        // Only now, so that a failed finish is retried on drop.
+        self.finished = true;
+        Ok(())
+    }
+}
+
+impl<W> Drop for HpglWriter<W>
+where
+    W: std::io::Write,
+{
+    fn drop(&mut self) {
+        // Errors can't be reported from drop, call finish to see them.
+        let _ = self.finish();
+    }
+}
+
+impl<A> HPGLable for PolyLine<2, A>
+where
+    A: AttributeHPGLPen,
+{
+    fn to_hpgl<W>(&self, w: &mut HpglWriter<W>) -> Result<(), std::io::Error>
+    where
+        W: std::io::Write,
+    {
+        if self.ps.len() <= 1 {
+            return Ok(());
+        }
+        w.select_pen(self.attributes.hpgl_pen())?;
+        w.stroke(&self.path_points())
+    }
+}
+
+impl<A> HPGLable for LineSegment<2, A>
+where
+    A: AttributeHPGLPen,
+{
+    fn to_hpgl<W>(&self, w: &mut HpglWriter<W>) -> Result<(), std::io::Error>
+    where
+        W: std::io::Write,
+    {
+        w.select_pen(self.attributes.hpgl_pen())?;
+        w.stroke(&self.ps)
+    }
+}
+
+impl HPGLable for LineSet {
+    fn to_hpgl<W>(&self, w: &mut HpglWriter<W>) -> Result<(), std::io::Error>
+    where
+        W: std::io::Write,
+    {
+        for l in &self.lines {
+            l.to_hpgl(w)?;
+        }
+        Ok(())
+    }
+}
+
+impl HPGLable for CubicBezierPath {
+    fn to_hpgl<W>(&self, w: &mut HpglWriter<W>) -> Result<(), std::io::Error>
+    where
+        W: std::io::Write,
+    {
+        if self.ps.len() <= 1 {
+            return Ok(());
+        }
+        let ps = self.flatten(w.options.bezier_tolerance).path_points();
+        w.select_pen(().hpgl_pen())?;
+        w.stroke(&ps)
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::n2::point::p2;
+    use crate::style::{Colour, StrokeStyle};
+
+    #[test]
+    pub fn polyline_to_hpgl() {
+        let mut out = vec![];
+        {
+            let options = HpglOptions {
+                origin: p2(0.0, -5.0),
+                height: 20.0,
+                ..Default::default()
+            };
+            let mut w = HpglWriter::new(&mut out, options).unwrap();
+            w.add(&PolyLine {
+                ps: vec![p2(0.0, 0.0), p2(10.0, 0.0), p2(10.0, 5.0)],
+                attributes: StrokeStyle::new(Colour::red()),
+                closed: false,
+            })
+            .unwrap();
+        }
+        let out = String::from_utf8(out).unwrap();
+        assert_eq!(out, "IN;\nSP2;\nPU0,600;\nPD400,600,400,400;\nPU;\nSP0;\n");
+
+        // The corners of the page map to the corners of the plot area.
+        let w = HpglWriter::new(vec![], HpglOptions::default()).unwrap();
+        assert_eq!(w.to_plotter_units(p2(0.0, 297.0)), (0, 0));
+        assert_eq!(w.to_plotter_units(p2(210.0, 0.0)), (8400, 11880));
+        let w = HpglWriter::new(
+            vec![],
+            HpglOptions {
+                flip_y: false,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(w.to_plotter_units(p2(210.0, 0.0)), (8400, 0));
+    }
+
+    #[test]
+    pub fn malformed_bezier_to_hpgl() {
+        // A straight segment with a stray point, flattened with no
+        // tolerance at all.
+        let path = CubicBezierPath {
+            ps: vec![
+                p2(0.0, 0.0),
+                p2(1.0, 0.0),
+                p2(2.0, 0.0),
+                p2(3.0, 0.0),
+                p2(9.0, 9.0),
+            ],
+        };
+        let options = HpglOptions {
+            bezier_tolerance: 0.0,
+            ..Default::default()
+        };
+        let mut out = vec![];
+        {
+            let mut w = HpglWriter::new(&mut out, options).unwrap();
+            w.add(&path).unwrap();
+        }
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("PU0,11880;\nPD120,11880;\n"));
+    }
+}
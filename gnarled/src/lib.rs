@@ -1,5 +1,13 @@
 pub mod attributes;
+pub mod dxf;
+pub mod ebb;
+pub mod gcode;
+pub mod hpgl;
+pub mod raster;
+pub mod sink;
+pub mod style;
 pub mod svg;
+pub mod svg_import;
 
 pub mod n2;
 pub mod n3;
@@ -47,13 +47,6 @@ pub struct ShadingV0 {
     pub rand: Box<dyn RandomField2D + Send + Sync>,
 }
 
-impl Bounds {
-    //TODO: This is not working yet!
-    pub fn clip(&self, ls: LineSegment<2, ()>) -> Option<LineSegment<2, ()>> {
-        Some(ls)
-    }
-}
-
 pub fn clip_by_mask(lsx: LineSegment<2, ()>, mask: &dyn Mask) -> LineSet {
     // For now we just do a linear split based on the values of the
     // end points. Later we might want to do something cleverer and
@@ -65,6 +58,7 @@ pub fn clip_by_mask(lsx: LineSegment<2, ()>, mask: &dyn Mask) -> LineSet {
             lines: vec![PolyLine {
                 ps: vec![lsx.ps[0], lsx.ps[1]],
                 attributes: (),
+                closed: false,
             }],
         }
     } else if (m0 < 0.0) && (m1 < 0.0) {
@@ -76,6 +70,7 @@ pub fn clip_by_mask(lsx: LineSegment<2, ()>, mask: &dyn Mask) -> LineSet {
             lines: vec![PolyLine {
                 ps: vec![lsx.ps[0], p],
                 attributes: (),
+                closed: false,
             }],
         }
     } else {
@@ -85,6 +80,7 @@ pub fn clip_by_mask(lsx: LineSegment<2, ()>, mask: &dyn Mask) -> LineSet {
             lines: vec![PolyLine {
                 ps: vec![p, lsx.ps[1]],
                 attributes: (),
+                closed: false,
             }],
         }
     }
@@ -2,7 +2,10 @@ pub mod bounds;
 pub mod cubic_bezier;
 pub mod hl;
 pub mod lineset;
+pub mod offset;
+pub mod paper;
 pub mod point;
+pub mod polygon;
 pub mod polyline;
 pub mod tile;
 pub mod traits;
@@ -0,0 +1,314 @@
+use crate::n2::{
+    lineset::LineSet,
+    point::{p2, Point},
+    polygon::{FillRule, Polygon},
+    polyline::PolyLine,
+};
+use crate::style::{LineCap, LineJoin};
+
+#[derive(Clone, Copy, Debug)]
+pub struct OffsetOptions {
+    pub join: LineJoin,
+    pub cap: LineCap,
+    // Miter joins sticking out further than this many times the offset
+    // distance are bevelled instead, as for SVG's stroke-miterlimit.
+    pub miter_limit: f32,
+    // How far round joins and caps may be from a true arc.
+    pub tolerance: f32,
+}
+
+impl Default for OffsetOptions {
+    fn default() -> Self {
+        OffsetOptions {
+            join: LineJoin::Miter,
+            cap: LineCap::Butt,
+            miter_limit: 4.0,
+            tolerance: 0.01,
+        }
+    }
+}
+
+fn cross(a: Point, b: Point) -> f32 {
+    a.vs[0] * b.vs[1] - a.vs[1] * b.vs[0]
+}
+
+// The left hand normal of a unit vector.
+fn normal(u: Point) -> Point {
+    p2(-u.vs[1], u.vs[0])
+}
+
+fn rotate(v: Point, angle: f32) -> Point {
+    let (s, c) = angle.sin_cos();
+    p2(c * v.vs[0] - s * v.vs[1], s * v.vs[0] + c * v.vs[1])
+}
+
+// A closed ring through the points, wound to have positive area so
+// that a set of them can be unioned with the non-zero rule.
+fn ring(mut ps: Vec<Point>) -> PolyLine<()> {
+    let area: f32 = ps
+        .iter()
+        .zip(ps.iter().cycle().skip(1))
+        .map(|(a, b)| cross(*a, *b))
+        .sum();
+    if area < 0.0 {
+        ps.reverse();
+    }
+    PolyLine {
+        ps,
+        attributes: (),
+        closed: true,
+    }
+}
+
+// Points on the arc of radius |v| round `c`, starting at c + v and
+// turning through `angle`, excluding the start.
+fn arc(c: Point, v: Point, angle: f32, tolerance: f32) -> Vec<Point> {
+    let r = v.norm_squared().sqrt();
+    let max_step = if tolerance < r {
+        2.0 * (1.0 - tolerance / r).acos()
+    } else {
+        std::f32::consts::FRAC_PI_2
+    };
+    let steps = (angle.abs() / max_step).ceil().max(1.0) as usize;
+    (1..=steps)
+        .map(|i| c + rotate(v, angle * i as f32 / steps as f32))
+        .collect()
+}
+
+// The pieces whose union is everything within `d` of the line through
+// `ps`: a rectangle along each segment, plus the joins and caps.
+fn buffer_pieces(ps: &[Point], closed: bool, d: f32, options: &OffsetOptions) -> Vec<PolyLine<()>> {
+    let mut ps: Vec<Point> = ps.to_vec();
+    ps.dedup();
+    if closed && ps.len() > 1 && ps.first() == ps.last() {
+        ps.pop();
+    }
+    if ps.len() < 2 {
+        return vec![];
+    }
+    let n = ps.len();
+    let segments = if closed { n } else { n - 1 };
+    let dirs: Vec<Point> = (0..segments)
+        .map(|i| (ps[(i + 1) % n] - ps[i]).normalize())
+        .collect();
+
+    let mut pieces = vec![];
+    for (i, u) in dirs.iter().enumerate() {
+        let (a, b) = (ps[i], ps[(i + 1) % n]);
+        let o = normal(*u) * d;
+        pieces.push(ring(vec![a + o, b + o, b - o, a - o]));
+    }
+
+    // The joins, on the outside of each turn.
+    let joins = if closed { 0..n } else { 1..n - 1 };
+    for i in joins {
+        let v = ps[i];
+        let (u1, u2) = (dirs[(i + segments - 1) % segments], dirs[i % segments]);
+        let turn = cross(u1, u2);
+        if turn.abs() < 1e-6 && u1.dot(u2) > 0.0 {
+            continue;
+        }
+        let side = if turn > 0.0 { -1.0 } else { 1.0 };
+        let (o1, o2) = (normal(u1) * (side * d), normal(u2) * (side * d));
+        let angle = cross(o1, o2).atan2(o1.dot(o2));
+        let cos = o1.dot(o2) / (d * d);
+        let join = match options.join {
+            LineJoin::Miter if (2.0 / (1.0 + cos)).sqrt() <= options.miter_limit => {
+                vec![v, v + o1, v + (o1 + o2) * (1.0 / (1.0 + cos)), v + o2]
+            }
+            LineJoin::Round => {
+                let mut join = vec![v, v + o1];
+                join.extend(arc(v, o1, angle, options.tolerance));
+                join
+            }
+            _ => vec![v, v + o1, v + o2],
+        };
+        pieces.push(ring(join));
+    }
+
+    if !closed {
+        for (end, u) in [(ps[0], -dirs[0]), (ps[n - 1], dirs[segments - 1])] {
+            let o = normal(u) * d;
+            match options.cap {
+                LineCap::Butt => {}
+                LineCap::Square => {
+                    let e = u * d;
+                    pieces.push(ring(vec![end + o, end + o + e, end - o + e, end - o]));
+                }
+                LineCap::Round => {
+                    let mut cap = vec![end + o];
+                    cap.extend(arc(end, o, -std::f32::consts::PI, options.tolerance));
+                    pieces.push(ring(cap));
+                }
+            }
+        }
+    }
+    pieces
+}
+
+impl<A> PolyLine<A> {
+    // Everything within `distance` of the line, with the ends finished
+    // off by `options.cap` unless the line is closed.
+    pub fn buffer(&self, distance: f32, options: &OffsetOptions) -> Vec<Polygon> {
+        if distance <= 0.0 {
+            return vec![];
+        }
+        Polygon::new(
+            buffer_pieces(&self.ps, self.closed, distance, options),
+            FillRule::NonZero,
+        )
+        .simplify()
+    }
+
+    // The outline of the line drawn with a pen `width` wide.
+    pub fn stroke_outline(&self, width: f32, options: &OffsetOptions) -> Vec<Polygon> {
+        self.buffer(width / 2.0, options)
+    }
+}
+
+impl Polygon {
+    // Grows the polygon by `distance`, or shrinks it if `distance` is
+    // negative. The joins are used at the corners, the caps aren't.
+    pub fn offset(&self, distance: f32, options: &OffsetOptions) -> Vec<Polygon> {
+        if distance == 0.0 {
+            return self.simplify();
+        }
+        let band = Polygon::new(
+            self.rings
+                .iter()
+                .flat_map(|r| buffer_pieces(&r.ps, true, distance.abs(), options))
+                .collect(),
+            FillRule::NonZero,
+        );
+        if distance > 0.0 {
+            self.union(&band)
+        } else {
+            self.difference(&band)
+        }
+    }
+
+    // The outlines of the polygon inset by `spacing`, 2 `spacing` and so
+    // on until nothing is left, for filling it with concentric rings.
+    pub fn concentric_fill(&self, spacing: f32, options: &OffsetOptions) -> LineSet {
+        let mut lines = vec![];
+        if spacing <= 0.0 {
+            return LineSet { lines };
+        }
+        let mut k = 1.0;
+        loop {
+            let inset = self.offset(-spacing * k, options);
+            if inset.is_empty() {
+                break;
+            }
+            lines.extend(inset.iter().flat_map(|p| p.outline().lines));
+            k += 1.0;
+        }
+        LineSet { lines }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    fn square() -> Polygon {
+        Polygon::from_polyline(&PolyLine {
+            ps: vec![p2(0.0, 0.0), p2(10.0, 0.0), p2(10.0, 10.0), p2(0.0, 10.0)],
+            attributes: (),
+            closed: true,
+        })
+    }
+
+    fn area(ps: &[Polygon]) -> f32 {
+        ps.iter().map(|p| p.area()).sum()
+    }
+
+    #[test]
+    pub fn offset_polygons() {
+        let miter = OffsetOptions::default();
+        let round = OffsetOptions {
+            join: LineJoin::Round,
+            ..miter
+        };
+        let bevel = OffsetOptions {
+            join: LineJoin::Bevel,
+            ..miter
+        };
+        let grown = square().offset(1.0, &miter);
+        assert_eq!(grown.len(), 1);
+        assert_eq!(grown[0].rings[0].ps.len(), 4);
+        assert!((area(&grown) - 144.0).abs() < 1e-3);
+        let grown = square().offset(1.0, &round);
+        assert!((area(&grown) - (140.0 + std::f32::consts::PI)).abs() < 0.05);
+        let grown = square().offset(1.0, &bevel);
+        assert!((area(&grown) - 142.0).abs() < 1e-3);
+
+        let shrunk = square().offset(-1.0, &round);
+        assert_eq!(shrunk[0].rings[0].ps.len(), 4);
+        assert!((area(&shrunk) - 64.0).abs() < 1e-3);
+
+        // Insets at 1, 2, 3 and 4; at 5 the square has gone.
+        assert_eq!(square().concentric_fill(1.0, &miter).lines.len(), 4);
+    }
+
+    #[test]
+    pub fn buffer_polyline() {
+        // An L, whose inside corner has to be resolved.
+        let l = PolyLine {
+            ps: vec![p2(0.0, 0.0), p2(10.0, 0.0), p2(10.0, 10.0)],
+            attributes: (),
+            closed: false,
+        };
+        let butt = OffsetOptions::default();
+        let outline = l.buffer(1.0, &butt);
+        assert_eq!(outline.len(), 1);
+        assert_eq!(outline[0].rings.len(), 1);
+        assert_eq!(outline[0].rings[0].ps.len(), 6);
+        assert!((area(&outline) - 40.0).abs() < 1e-3);
+
+        let square = OffsetOptions {
+            cap: LineCap::Square,
+            ..butt
+        };
+        assert!((area(&l.buffer(1.0, &square)) - 44.0).abs() < 1e-3);
+
+        // Closing it puts a hole in the middle.
+        let closed = PolyLine { closed: true, ..l };
+        let outline = closed.stroke_outline(2.0, &butt);
+        assert_eq!(outline.len(), 1);
+        assert_eq!(outline[0].rings.len(), 2);
+
+        // Doubling back leaves long, nearly parallel edges to resolve.
+        let back = PolyLine {
+            ps: vec![p2(4.208, 3.38), p2(9.964, 3.649), p2(2.607, 3.306)],
+            attributes: (),
+            closed: false,
+        };
+        let round = OffsetOptions {
+            join: LineJoin::Round,
+            cap: LineCap::Round,
+            ..butt
+        };
+        let outline = back.buffer(0.9, &round);
+        assert_eq!(outline.len(), 1);
+        assert_eq!(outline[0].rings.len(), 1);
+        let longest = 2.0 * 0.9 * (p2(9.964, 3.649) - p2(2.607, 3.306)).norm_squared().sqrt();
+        assert!(area(&outline) > longest);
+
+        // A long wiggly line, whose many joins and rectangles meet at
+        // points too close together to keep apart.
+        let wiggle = PolyLine {
+            ps: (0..200)
+                .map(|i| {
+                    let t = i as f32 * 0.07;
+                    p2(t * 10.0, (t * 7.0).sin() * 5.0)
+                })
+                .collect(),
+            attributes: (),
+            closed: false,
+        };
+        let outline = wiggle.stroke_outline(1.0, &butt);
+        assert_eq!(outline.len(), 1);
+        assert!((area(&outline) - wiggle.length()).abs() < 0.1);
+    }
+}
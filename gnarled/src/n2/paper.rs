@@ -0,0 +1,197 @@
+use crate::n2::bounds::Bounds;
+use crate::n2::point::{p2, Point};
+use crate::n2::{lineset::LineSet, tile::clip_to_rect};
+use crate::nbase::traits::*;
+use crate::svg::PageSize;
+
+// Standard paper sizes. Dimensions are in mm, portrait.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PaperSize {
+    A5,
+    A4,
+    A3,
+    A2,
+    A1,
+    Letter,
+    Tabloid,
+    Custom { width: f32, height: f32 },
+}
+
+impl PaperSize {
+    pub fn dimensions(&self) -> (f32, f32) {
+        match self {
+            PaperSize::A5 => (148.0, 210.0),
+            PaperSize::A4 => (210.0, 297.0),
+            PaperSize::A3 => (297.0, 420.0),
+            PaperSize::A2 => (420.0, 594.0),
+            PaperSize::A1 => (594.0, 841.0),
+            PaperSize::Letter => (215.9, 279.4),
+            PaperSize::Tabloid => (279.4, 431.8),
+            PaperSize::Custom { width, height } => (*width, *height),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Orientation {
+    Portrait,
+    Landscape,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Paper {
+    pub size: PaperSize,
+    pub orientation: Orientation,
+}
+
+// Margins in mm.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Margins {
+    pub top: f32,
+    pub right: f32,
+    pub bottom: f32,
+    pub left: f32,
+}
+
+impl Margins {
+    pub fn uniform(m: f32) -> Margins {
+        Margins {
+            top: m,
+            right: m,
+            bottom: m,
+            left: m,
+        }
+    }
+}
+
+impl Paper {
+    pub fn new(size: PaperSize, orientation: Orientation) -> Paper {
+        Paper { size, orientation }
+    }
+
+    // Width and height in mm, taking the orientation into account.
+    pub fn dimensions(&self) -> Point {
+        let (w, h) = self.size.dimensions();
+        let (short, long) = (w.min(h), w.max(h));
+        match self.orientation {
+            Orientation::Portrait => p2(short, long),
+            Orientation::Landscape => p2(long, short),
+        }
+    }
+
+    pub fn page_size(&self) -> PageSize {
+        let d = self.dimensions();
+        PageSize::mm(d.vs[0], d.vs[1])
+    }
+
+    // The whole page, in mm. Use with `page_size` so that one
+    // unit in the SVG is one mm on the paper.
+    pub fn view_box(&self) -> Bounds {
+        Bounds {
+            min: Point::zero(),
+            max: self.dimensions(),
+        }
+    }
+
+    pub fn printable_area(&self, margins: &Margins) -> Bounds {
+        let d = self.dimensions();
+        Bounds {
+            min: p2(margins.left, margins.top),
+            max: p2(d.vs[0] - margins.right, d.vs[1] - margins.bottom),
+        }
+    }
+}
+
+// Uniformly scales and centres `lines` to fill the printable area of the page.
+pub fn fit_to_page(lines: &LineSet, paper: &Paper, margins: &Margins) -> LineSet {
+    match lines.bounds() {
+        Some(bounds) => fit_bounds_to_page(lines, bounds, paper, margins, false),
+        None => lines.clone(),
+    }
+}
+
+// Uniformly scales and centres the region `source` (e.g. the viewBox the
+// artwork was drawn in) to fill the printable area of the page. If `clip`
+// is set, anything that ends up outside the printable area is removed.
+pub fn fit_bounds_to_page(
+    lines: &LineSet,
+    source: Bounds,
+    paper: &Paper,
+    margins: &Margins,
+    clip: bool,
+) -> LineSet {
+    let area = paper.printable_area(margins);
+    let source_size = source.max - source.min;
+    let area_size = area.max - area.min;
+
+    // Degenerate (zero width or height) sources are scaled by the other axis.
+    let scales = [0, 1]
+        .iter()
+        .filter(|&&i| source_size.vs[i] > 0.0)
+        .map(|&i| area_size.vs[i] / source_size.vs[i]);
+    let s = scales.fold(f32::INFINITY, f32::min);
+    let s = if s.is_finite() { s } else { 1.0 };
+
+    let source_center = Point::lerp(0.5, source.min, source.max);
+    let area_center = Point::lerp(0.5, area.min, area.max);
+    let result = lines
+        .shift_by(source_center.neg())
+        .scale(Point::zero(), &[s, s])
+        .shift_by(area_center);
+    if clip {
+        clip_to_rect(&result, (area.min, area.max))
+    } else {
+        result
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::n2::polyline::PolyLine;
+
+    #[test]
+    pub fn fit_centres_and_scales() {
+        let lines = LineSet {
+            lines: vec![PolyLine {
+                ps: vec![p2(0.0, 0.0), p2(800.0, 400.0)],
+                attributes: (),
+                closed: false,
+            }],
+        };
+        let paper = Paper::new(PaperSize::A4, Orientation::Portrait);
+        let fitted = fit_to_page(&lines, &paper, &Margins::uniform(10.0));
+        let b = fitted.bounds().unwrap();
+        // Limited by the width: 190mm across, so 95mm high, centred vertically.
+        assert!((b.min.vs[0] - 10.0).abs() < 1e-3);
+        assert!((b.max.vs[0] - 200.0).abs() < 1e-3);
+        assert!((b.min.vs[1] - (297.0 - 95.0) / 2.0).abs() < 1e-3);
+        assert!((b.max.vs[1] - (297.0 + 95.0) / 2.0).abs() < 1e-3);
+    }
+
+    #[test]
+    pub fn fit_bounds_clips_overhang() {
+        let lines = LineSet {
+            lines: vec![PolyLine {
+                ps: vec![p2(-100.0, 50.0), p2(200.0, 50.0)],
+                attributes: (),
+                closed: false,
+            }],
+        };
+        let source = Bounds {
+            min: p2(0.0, 0.0),
+            max: p2(100.0, 100.0),
+        };
+        let paper = Paper::new(
+            PaperSize::Custom {
+                width: 100.0,
+                height: 100.0,
+            },
+            Orientation::Portrait,
+        );
+        let fitted = fit_bounds_to_page(&lines, source, &paper, &Margins::default(), true);
+        let b = fitted.bounds().unwrap();
+        assert!((b.min.vs[0] - 0.0).abs() < 1e-3);
+        assert!((b.max.vs[0] - 100.0).abs() < 1e-3);
+    }
+}
@@ -0,0 +1,919 @@
+use std::collections::HashMap;
+
+use crate::n2::{bounds::Bounds, hl::Mask, lineset::LineSet, point::Point, polyline::PolyLine};
+use crate::nbase::polyline::PolyLineAttribute;
+use crate::nbase::traits::Boundable;
+
+// The boolean operations are done in f64, only converting back at the end.
+type P64 = crate::nbase::point::Point<2, f64>;
+
+fn to_f64(p: Point) -> P64 {
+    p.map(|v| v as f64)
+}
+
+fn to_f32(p: P64) -> Point {
+    p.map(|v| v as f32)
+}
+
+fn cross(a: P64, b: P64) -> f64 {
+    a.vs[0] * b.vs[1] - a.vs[1] * b.vs[0]
+}
+
+// How the winding number of a point decides if it is inside, as for
+// SVG's `fill-rule`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FillRule {
+    EvenOdd,
+    NonZero,
+}
+
+impl FillRule {
+    pub fn is_inside(&self, winding: i32) -> bool {
+        match self {
+            FillRule::EvenOdd => winding % 2 != 0,
+            FillRule::NonZero => winding != 0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BooleanOp {
+    Union,
+    Intersection,
+    Difference,
+    Xor,
+}
+
+impl BooleanOp {
+    fn apply(&self, a: bool, b: bool) -> bool {
+        match self {
+            BooleanOp::Union => a || b,
+            BooleanOp::Intersection => a && b,
+            BooleanOp::Difference => a && !b,
+            BooleanOp::Xor => a != b,
+        }
+    }
+}
+
+// A region of the plane bounded by closed rings, e.g. an outline and
+// the holes in it. The rings are always treated as closed, whatever
+// their `closed` flag says.
+//
+// Polygons returned by the boolean operations have a single outer ring
+// with positive signed area, followed by its holes with negative signed
+// area, and none of the rings cross.
+#[derive(Clone, Debug)]
+pub struct Polygon {
+    pub rings: Vec<PolyLine<()>>,
+    pub fill_rule: FillRule,
+}
+
+impl Polygon {
+    pub fn new(rings: Vec<PolyLine<()>>, fill_rule: FillRule) -> Polygon {
+        Polygon { rings, fill_rule }
+    }
+
+    pub fn from_polyline<A>(pl: &PolyLine<A>) -> Polygon {
+        Polygon {
+            rings: vec![pl.map_attribute(|_| ())],
+            fill_rule: FillRule::NonZero,
+        }
+    }
+
+    fn rings64(&self) -> Vec<Vec<P64>> {
+        self.rings
+            .iter()
+            .filter(|r| r.ps.len() >= 3)
+            .map(|r| r.ps.iter().map(|p| to_f64(*p)).collect())
+            .collect()
+    }
+
+    pub fn winding_number(&self, p: Point) -> i32 {
+        winding_number(&self.rings64(), to_f64(p))
+    }
+
+    pub fn contains(&self, p: Point) -> bool {
+        self.fill_rule.is_inside(self.winding_number(p))
+    }
+
+    // The sum of the signed areas of the rings.
+    pub fn area(&self) -> f32 {
+        self.rings64().iter().map(|r| signed_area(r)).sum::<f64>() as f32
+    }
+
+    // The rings as closed lines, for drawing the outline.
+    pub fn outline(&self) -> LineSet {
+        LineSet {
+            lines: self
+                .rings
+                .iter()
+                .map(|r| PolyLine {
+                    ps: r.ps.clone(),
+                    attributes: (),
+                    closed: true,
+                })
+                .collect(),
+        }
+    }
+
+    // The same area as simple polygons, with any self-intersections and
+    // overlapping rings resolved.
+    pub fn simplify(&self) -> Vec<Polygon> {
+        boolean(
+            self,
+            &Polygon::new(vec![], self.fill_rule),
+            BooleanOp::Union,
+        )
+    }
+
+    pub fn boolean(&self, other: &Polygon, op: BooleanOp) -> Vec<Polygon> {
+        boolean(self, other, op)
+    }
+
+    pub fn union(&self, other: &Polygon) -> Vec<Polygon> {
+        boolean(self, other, BooleanOp::Union)
+    }
+
+    pub fn intersection(&self, other: &Polygon) -> Vec<Polygon> {
+        boolean(self, other, BooleanOp::Intersection)
+    }
+
+    pub fn difference(&self, other: &Polygon) -> Vec<Polygon> {
+        boolean(self, other, BooleanOp::Difference)
+    }
+
+    pub fn xor(&self, other: &Polygon) -> Vec<Polygon> {
+        boolean(self, other, BooleanOp::Xor)
+    }
+}
+
+impl Boundable<2> for Polygon {
+    fn bounds(&self) -> Option<Bounds> {
+        self.rings
+            .iter()
+            .flat_map(|r| r.ps.iter())
+            .fold(None, crate::nbase::point::point_extrema)
+    }
+}
+
+// Positive inside, negative outside.
+impl Mask for Polygon {
+    fn mask(&self, p: Point) -> f32 {
+        if self.contains(p) {
+            1.0
+        } else {
+            -1.0
+        }
+    }
+}
+
+fn ring_edges(ring: &[P64]) -> impl Iterator<Item = (P64, P64)> + '_ {
+    ring.iter()
+        .zip(ring.iter().cycle().skip(1))
+        .map(|(a, b)| (*a, *b))
+}
+
+fn winding_number(rings: &[Vec<P64>], p: P64) -> i32 {
+    let mut w = 0;
+    for ring in rings {
+        for (a, b) in ring_edges(ring) {
+            let left = cross(b - a, p - a);
+            if a.vs[1] <= p.vs[1] {
+                if b.vs[1] > p.vs[1] && left > 0.0 {
+                    w += 1;
+                }
+            } else if b.vs[1] <= p.vs[1] && left < 0.0 {
+                w -= 1;
+            }
+        }
+    }
+    w
+}
+
+fn signed_area(ring: &[P64]) -> f64 {
+    ring_edges(ring).map(|(a, b)| cross(a, b)).sum::<f64>() / 2.0
+}
+
+// Where two edges meet, as (parameter on the first, parameter on the
+// second, point). Points within `tol` of an end are snapped to it, so
+// both edges get split at exactly the same place.
+fn intersect(e1: (P64, P64), e2: (P64, P64), tol: f64) -> Vec<(f64, f64, P64)> {
+    let (a1, b1) = e1;
+    let (a2, b2) = e2;
+    let d1 = b1 - a1;
+    let d2 = b2 - a2;
+    let l1 = d1.norm_squared().sqrt();
+    let l2 = d2.norm_squared().sqrt();
+    let (lo1, hi1) = (
+        P64::componentwise_min(a1, b1),
+        P64::componentwise_max(a1, b1),
+    );
+    let (lo2, hi2) = (
+        P64::componentwise_min(a2, b2),
+        P64::componentwise_max(a2, b2),
+    );
+    for i in 0..2 {
+        if lo1.vs[i] > hi2.vs[i] + tol || lo2.vs[i] > hi1.vs[i] + tol {
+            return vec![];
+        }
+    }
+
+    if (cross(d1, a2 - a1) / l1).abs() <= tol && (cross(d1, b2 - a1) / l1).abs() <= tol {
+        // Collinear, so split each edge where the other one ends.
+        let mut result = vec![];
+        for (u, q) in [(0.0, a2), (1.0, b2)] {
+            let t = (q - a1).dot(d1) / (l1 * l1);
+            if t * l1 > tol && (1.0 - t) * l1 > tol {
+                result.push((t, u, q));
+            }
+        }
+        for (t, q) in [(0.0, a1), (1.0, b1)] {
+            let u = (q - a2).dot(d2) / (l2 * l2);
+            if u * l2 > tol && (1.0 - u) * l2 > tol {
+                result.push((t, u, q));
+            }
+        }
+        return result;
+    }
+
+    let denom = cross(d1, d2);
+    if denom == 0.0 {
+        return vec![];
+    }
+    let t = cross(a2 - a1, d2) / denom;
+    let u = cross(a2 - a1, d1) / denom;
+    let (te1, te2) = (tol / l1, tol / l2);
+    if t < -te1 || t > 1.0 + te1 || u < -te2 || u > 1.0 + te2 {
+        return vec![];
+    }
+    let p = if t <= te1 {
+        a1
+    } else if t >= 1.0 - te1 {
+        b1
+    } else if u <= te2 {
+        a2
+    } else if u >= 1.0 - te2 {
+        b2
+    } else {
+        a1 + d1 * t
+    };
+    vec![(t.clamp(0.0, 1.0), u.clamp(0.0, 1.0), p)]
+}
+
+// Gives points within `tol` of each other the same index. A point looked
+// up again always gets the index it got the first time, even if a point
+// added since is nearer.
+struct Vertices {
+    tol: f64,
+    cells: HashMap<(i64, i64), Vec<usize>>,
+    seen: HashMap<(u64, u64), usize>,
+    points: Vec<P64>,
+}
+
+impl Vertices {
+    fn new(tol: f64) -> Vertices {
+        Vertices {
+            tol,
+            cells: HashMap::new(),
+            seen: HashMap::new(),
+            points: vec![],
+        }
+    }
+
+    fn index(&mut self, p: P64) -> usize {
+        let key = (p.vs[0].to_bits(), p.vs[1].to_bits());
+        if let Some(id) = self.seen.get(&key) {
+            return *id;
+        }
+        let id = self.nearby(p);
+        self.seen.insert(key, id);
+        id
+    }
+
+    fn nearby(&mut self, p: P64) -> usize {
+        let cell = (
+            (p.vs[0] / self.tol).floor() as i64,
+            (p.vs[1] / self.tol).floor() as i64,
+        );
+        for dx in -1..=1 {
+            for dy in -1..=1 {
+                if let Some(ids) = self.cells.get(&(cell.0 + dx, cell.1 + dy)) {
+                    for id in ids {
+                        if (self.points[*id] - p).norm_squared() <= self.tol * self.tol {
+                            return *id;
+                        }
+                    }
+                }
+            }
+        }
+        let id = self.points.len();
+        self.points.push(p);
+        self.cells.entry(cell).or_default().push(id);
+        id
+    }
+}
+
+// Pieces bucketed by which of a set of equal bands of one coordinate
+// they span, so a ray along the other axis need only be tested against
+// the pieces in the band it runs along.
+struct Bands {
+    min: f64,
+    size: f64,
+    bands: Vec<Vec<usize>>,
+}
+
+impl Bands {
+    fn new(spans: &[(f64, f64)]) -> Bands {
+        let count = (spans.len() as f64).sqrt().ceil().max(1.0) as usize;
+        let min = spans.iter().map(|s| s.0).fold(f64::INFINITY, f64::min);
+        let max = spans.iter().map(|s| s.1).fold(f64::NEG_INFINITY, f64::max);
+        let mut bands = Bands {
+            min,
+            size: ((max - min) / count as f64).max(f64::MIN_POSITIVE),
+            bands: vec![vec![]; count],
+        };
+        for (n, (lo, hi)) in spans.iter().enumerate() {
+            for b in bands.band(*lo)..=bands.band(*hi) {
+                bands.bands[b].push(n);
+            }
+        }
+        bands
+    }
+
+    fn band(&self, v: f64) -> usize {
+        (((v - self.min) / self.size) as usize).min(self.bands.len() - 1)
+    }
+
+    fn near(&self, v: f64) -> &[usize] {
+        &self.bands[self.band(v)]
+    }
+}
+
+// The clockwise angle from `from` round to `to`, in (0, 2 pi].
+fn clockwise_angle(from: P64, to: P64) -> f64 {
+    let a = -cross(from, to).atan2(from.dot(to));
+    if a <= 0.0 {
+        a + 2.0 * std::f64::consts::PI
+    } else {
+        a
+    }
+}
+
+// Drops vertices that lie on the line between their neighbours.
+fn remove_collinear(mut ring: Vec<P64>, tol: f64) -> Vec<P64> {
+    let mut i = 0;
+    while ring.len() >= 3 && i < ring.len() {
+        let n = ring.len();
+        let (a, p, b) = (ring[(i + n - 1) % n], ring[i], ring[(i + 1) % n]);
+        let (u, v) = (p - a, b - p);
+        let scale = u.norm_squared().sqrt() + v.norm_squared().sqrt();
+        if cross(u, v).abs() <= tol * scale && u.dot(v) >= 0.0 {
+            ring.remove(i);
+            i = i.saturating_sub(1);
+        } else {
+            i += 1;
+        }
+    }
+    ring
+}
+
+// The boolean operation is done by splitting every edge of both polygons
+// wherever it meets another edge, then keeping the pieces with the result
+// inside on one side and outside on the other. The pieces are oriented
+// with the inside on their left and joined back up into rings.
+fn boolean(a: &Polygon, b: &Polygon, op: BooleanOp) -> Vec<Polygon> {
+    let (ra, rb) = (a.rings64(), b.rings64());
+    // Each edge with the polygon it came from, 0 for `a` and 1 for `b`.
+    let edges: Vec<(P64, P64, usize)> = ra
+        .iter()
+        .map(|r| (r, 0))
+        .chain(rb.iter().map(|r| (r, 1)))
+        .flat_map(|(r, k)| ring_edges(r).map(move |(p, q)| (p, q, k)))
+        .filter(|(p, q, _)| p != q)
+        .collect();
+    let extent = match edges
+        .iter()
+        .flat_map(|(p, q, _)| [p, q])
+        .fold(None, crate::nbase::point::point_extrema)
+    {
+        Some(b) => (b.max - b.min).max().max(f64::MIN_POSITIVE),
+        None => return vec![],
+    };
+    // Input points are only f32, so anything closer than this is the same.
+    let tol = extent * 1e-6;
+
+    // Where each edge is split, besides its ends.
+    let mut splits: Vec<Vec<(f64, P64)>> = vec![vec![]; edges.len()];
+    // Sweep across in x, so only edges whose bounding boxes overlap (to
+    // within tol) are tested against each other.
+    let boxes: Vec<(P64, P64)> = edges
+        .iter()
+        .map(|(p, q, _)| {
+            (
+                P64::componentwise_min(*p, *q),
+                P64::componentwise_max(*p, *q),
+            )
+        })
+        .collect();
+    let mut by_x: Vec<usize> = (0..edges.len()).collect();
+    by_x.sort_by(|a, b| boxes[*a].0.vs[0].total_cmp(&boxes[*b].0.vs[0]));
+    for (k, &i) in by_x.iter().enumerate() {
+        for &j in &by_x[k + 1..] {
+            if boxes[j].0.vs[0] > boxes[i].1.vs[0] + tol {
+                break;
+            }
+            if boxes[j].0.vs[1] > boxes[i].1.vs[1] + tol
+                || boxes[i].0.vs[1] > boxes[j].1.vs[1] + tol
+            {
+                continue;
+            }
+            let (e1, e2) = ((edges[i].0, edges[i].1), (edges[j].0, edges[j].1));
+            for (t, u, p) in intersect(e1, e2, tol) {
+                splits[i].push((t, p));
+                splits[j].push((u, p));
+            }
+        }
+    }
+
+    // Pieces run from the lower to the higher vertex index, with how many
+    // times each polygon's edges run along them that way (less the
+    // other way), which is how much its winding number goes up crossing
+    // them from right to left.
+    let mut vertices = Vertices::new(tol);
+    let mut pieces: HashMap<(usize, usize), [i32; 2]> = HashMap::new();
+    for (mut split, (p, q, k)) in splits.into_iter().zip(edges.iter()) {
+        split.sort_by(|x, y| x.0.total_cmp(&y.0));
+        // The ends stay at the ends, even where a split snapped to the
+        // other edge's end sorts beyond them, so every edge's pieces run
+        // from its start to its end.
+        let ids: Vec<usize> = std::iter::once(*p)
+            .chain(split.iter().map(|(_, s)| *s))
+            .chain(std::iter::once(*q))
+            .map(|s| vertices.index(s))
+            .collect();
+        for w in ids.windows(2) {
+            if w[0] != w[1] {
+                let piece = pieces.entry((w[0].min(w[1]), w[0].max(w[1]))).or_default();
+                piece[*k] += if w[0] < w[1] { 1 } else { -1 };
+            }
+        }
+    }
+    let points = vertices.points;
+    let pieces: Vec<((usize, usize), [i32; 2])> =
+        pieces.into_iter().filter(|(_, w)| *w != [0, 0]).collect();
+
+    // The winding numbers either side of each piece are counted along a
+    // ray from its midpoint, in x unless the piece runs that way, when it
+    // is in y. Using the pieces rather than the original edges keeps this
+    // consistent with the snapping.
+    let spans = |axis: usize| -> Vec<(f64, f64)> {
+        pieces
+            .iter()
+            .map(|((i, j), _)| {
+                let (a, b) = (points[*i].vs[axis], points[*j].vs[axis]);
+                (a.min(b), a.max(b))
+            })
+            .collect()
+    };
+    let (rows, columns) = (Bands::new(&spans(1)), Bands::new(&spans(0)));
+    let mut directed = vec![];
+    for (n, ((i, j), w)) in pieces.iter().enumerate() {
+        let (p, q) = (points[*i], points[*j]);
+        let m = P64::lerp(0.5, p, q);
+        let (ray, near) = if p.vs[1] != q.vs[1] {
+            (P64::from([1.0, 0.0]), rows.near(m.vs[1]))
+        } else {
+            (P64::from([0.0, 1.0]), columns.near(m.vs[0]))
+        };
+        let mut crossed = [0, 0];
+        for &o in near {
+            if o == n {
+                continue;
+            }
+            let ((oi, oj), ow) = &pieces[o];
+            // In coordinates with the ray along the x axis.
+            let (a, b) = (points[*oi] - m, points[*oj] - m);
+            let (ya, yb) = (cross(ray, a), cross(ray, b));
+            if (ya > 0.0) == (yb > 0.0) {
+                continue;
+            }
+            let x = a.dot(ray) + (b.dot(ray) - a.dot(ray)) * ya / (ya - yb);
+            if x > 0.0 {
+                let up = if yb > ya { 1 } else { -1 };
+                crossed[0] += up * ow[0];
+                crossed[1] += up * ow[1];
+            }
+        }
+        let (left, right) = if cross(q - p, ray) > 0.0 {
+            (crossed, [crossed[0] - w[0], crossed[1] - w[1]])
+        } else {
+            ([crossed[0] + w[0], crossed[1] + w[1]], crossed)
+        };
+        let inside =
+            |w: [i32; 2]| op.apply(a.fill_rule.is_inside(w[0]), b.fill_rule.is_inside(w[1]));
+        match (inside(left), inside(right)) {
+            (true, false) => directed.push((*i, *j)),
+            (false, true) => directed.push((*j, *i)),
+            _ => {}
+        }
+    }
+
+    let mut outgoing: HashMap<usize, Vec<usize>> = HashMap::new();
+    for (e, (i, _)) in directed.iter().enumerate() {
+        outgoing.entry(*i).or_default().push(e);
+    }
+    let mut used = vec![false; directed.len()];
+    let mut rings = vec![];
+    for start in 0..directed.len() {
+        if used[start] {
+            continue;
+        }
+        let mut ring = vec![];
+        let mut e = start;
+        let complete = loop {
+            used[e] = true;
+            let (i, j) = directed[e];
+            ring.push(points[i]);
+            if j == directed[start].0 {
+                break true;
+            }
+            // Turning as far right as possible keeps rings that only touch
+            // at a vertex apart.
+            let back = points[i] - points[j];
+            let next = outgoing
+                .get(&j)
+                .into_iter()
+                .flatten()
+                .filter(|n| !used[**n])
+                .min_by(|x, y| {
+                    let ax = clockwise_angle(back, points[directed[**x].1] - points[j]);
+                    let ay = clockwise_angle(back, points[directed[**y].1] - points[j]);
+                    ax.total_cmp(&ay)
+                });
+            match next {
+                Some(n) => e = *n,
+                None => break false,
+            }
+        };
+        let ring = remove_collinear(ring, tol);
+        if complete && ring.len() >= 3 && signed_area(&ring).abs() > tol * extent {
+            rings.push(ring);
+        }
+    }
+
+    let (outers, holes): (Vec<Vec<P64>>, Vec<Vec<P64>>) =
+        rings.into_iter().partition(|r| signed_area(r) > 0.0);
+    let mut result: Vec<Vec<Vec<P64>>> = outers.into_iter().map(|r| vec![r]).collect();
+    for hole in holes {
+        // Holes can touch their outer ring at a vertex, but not along an
+        // edge, so the middle of an edge is strictly inside it.
+        let probe = P64::lerp(0.5, hole[0], hole[1]);
+        let parent = result
+            .iter_mut()
+            .filter(|rs| winding_number(&rs[..1], probe) != 0)
+            .min_by(|x, y| signed_area(&x[0]).total_cmp(&signed_area(&y[0])));
+        if let Some(parent) = parent {
+            parent.push(hole);
+        }
+    }
+
+    result
+        .into_iter()
+        .map(|rs| Polygon {
+            rings: rs
+                .into_iter()
+                .map(|r| PolyLine {
+                    ps: r.into_iter().map(to_f32).collect(),
+                    attributes: (),
+                    closed: true,
+                })
+                .collect(),
+            fill_rule: FillRule::NonZero,
+        })
+        .collect()
+}
+
+// Part of a line kept by clipping, with its ends given as
+// (segment index, parameter along the segment).
+struct Piece {
+    start: (usize, f32),
+    ps: Vec<Point>,
+    end: (usize, f32),
+}
+
+// Clips `pl` to the parts inside (or outside) `polygon`. The pieces get
+// their attributes from `poly_range`, and a closed line that is cut keeps
+// the piece through its first point in one piece.
+fn clip_to_polygon<A>(pl: &PolyLine<A>, polygon: &Polygon, inside: bool) -> Vec<PolyLine<A>>
+where
+    A: PolyLineAttribute,
+{
+    let ps = pl.path_points();
+    if ps.len() < 2 {
+        return vec![];
+    }
+    let rings = polygon.rings64();
+    let edges: Vec<(P64, P64)> = rings
+        .iter()
+        .flat_map(|r| ring_edges(r))
+        .filter(|(p, q)| p != q)
+        .collect();
+    let extent = match ps
+        .iter()
+        .chain(polygon.rings.iter().flat_map(|r| r.ps.iter()))
+        .fold(None, crate::nbase::point::point_extrema)
+    {
+        Some(b) => (b.max - b.min).max().max(f32::MIN_POSITIVE) as f64,
+        None => return vec![],
+    };
+    let tol = extent * 1e-9;
+    let keep = |p: P64| polygon.fill_rule.is_inside(winding_number(&rings, p)) == inside;
+
+    let mut pieces: Vec<Piece> = vec![];
+    let mut current: Option<Piece> = None;
+    for i in 0..ps.len() - 1 {
+        let (a, b) = (to_f64(ps[i]), to_f64(ps[i + 1]));
+        let mut ts = vec![0.0, 1.0];
+        if a != b {
+            for e in &edges {
+                ts.extend(intersect((a, b), *e, tol).into_iter().map(|(t, _, _)| t));
+            }
+        }
+        ts.sort_by(|x, y| x.total_cmp(y));
+        ts.dedup();
+        for w in ts.windows(2) {
+            let (t0, t1) = (w[0] as f32, w[1] as f32);
+            if keep(P64::lerp(0.5 * (w[0] + w[1]), a, b)) {
+                let piece = current.get_or_insert_with(|| Piece {
+                    start: (i, t0),
+                    ps: vec![to_f32(P64::lerp(w[0], a, b))],
+                    end: (i, t0),
+                });
+                piece.ps.push(to_f32(P64::lerp(w[1], a, b)));
+                piece.end = (i, t1);
+            } else if let Some(piece) = current.take() {
+                pieces.push(piece);
+            }
+        }
+    }
+    pieces.extend(current);
+
+    let end = (ps.len() - 2, 1.0);
+    if pl.closed
+        && !pieces.is_empty()
+        && pieces[0].start == (0, 0.0)
+        && pieces.last().unwrap().end == end
+    {
+        if pieces.len() == 1 {
+            // Nothing was clipped away.
+            return vec![PolyLine {
+                ps: pl.ps.clone(),
+                attributes: pl.attributes.poly_range((0, 0.0), end),
+                closed: true,
+            }];
+        }
+        let first = pieces.remove(0);
+        let last = pieces.last_mut().unwrap();
+        last.ps.extend_from_slice(&first.ps[1..]);
+        last.end = first.end;
+    }
+
+    pieces
+        .into_iter()
+        .map(|piece| PolyLine {
+            ps: piece.ps,
+            attributes: pl.attributes.poly_range(piece.start, piece.end),
+            closed: false,
+        })
+        .collect()
+}
+
+impl<A> PolyLine<A> {
+    // The parts of the line inside the polygon.
+    pub fn clip_inside(&self, polygon: &Polygon) -> Vec<PolyLine<A>>
+    where
+        A: PolyLineAttribute,
+    {
+        clip_to_polygon(self, polygon, true)
+    }
+
+    // The parts of the line outside the polygon.
+    pub fn clip_outside(&self, polygon: &Polygon) -> Vec<PolyLine<A>>
+    where
+        A: PolyLineAttribute,
+    {
+        clip_to_polygon(self, polygon, false)
+    }
+}
+
+impl LineSet {
+    pub fn clip_inside(&self, polygon: &Polygon) -> LineSet {
+        LineSet {
+            lines: self
+                .lines
+                .iter()
+                .flat_map(|line| line.clip_inside(polygon))
+                .collect(),
+        }
+    }
+
+    pub fn clip_outside(&self, polygon: &Polygon) -> LineSet {
+        LineSet {
+            lines: self
+                .lines
+                .iter()
+                .flat_map(|line| line.clip_outside(polygon))
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::n2::point::p2;
+
+    fn rect(x0: f32, y0: f32, x1: f32, y1: f32) -> PolyLine<()> {
+        PolyLine {
+            ps: vec![p2(x0, y0), p2(x1, y0), p2(x1, y1), p2(x0, y1)],
+            attributes: (),
+            closed: true,
+        }
+    }
+
+    fn total_area(ps: &[Polygon]) -> f32 {
+        ps.iter().map(|p| p.area()).sum()
+    }
+
+    #[test]
+    pub fn overlapping_squares() {
+        let a = Polygon::from_polyline(&rect(0.0, 0.0, 10.0, 10.0));
+        let b = Polygon::from_polyline(&rect(5.0, 5.0, 15.0, 15.0));
+
+        let union = a.union(&b);
+        assert_eq!(union.len(), 1);
+        assert_eq!(union[0].rings.len(), 1);
+        assert_eq!(union[0].rings[0].ps.len(), 8);
+        assert!((total_area(&union) - 175.0).abs() < 1e-3);
+
+        let intersection = a.intersection(&b);
+        assert_eq!(intersection.len(), 1);
+        assert_eq!(intersection[0].rings[0].ps.len(), 4);
+        assert!((total_area(&intersection) - 25.0).abs() < 1e-3);
+        assert!(intersection[0].contains(p2(7.5, 7.5)));
+        assert!(!intersection[0].contains(p2(2.5, 2.5)));
+
+        assert!((total_area(&a.difference(&b)) - 75.0).abs() < 1e-3);
+        let xor = a.xor(&b);
+        assert_eq!(xor.len(), 2);
+        assert!((total_area(&xor) - 150.0).abs() < 1e-3);
+    }
+
+    #[test]
+    pub fn holes_and_fill_rules() {
+        // Both rings wound the same way: a hole under even-odd only.
+        let rings = vec![rect(0.0, 0.0, 10.0, 10.0), rect(3.0, 3.0, 7.0, 7.0)];
+        let even_odd = Polygon::new(rings.clone(), FillRule::EvenOdd);
+        let non_zero = Polygon::new(rings, FillRule::NonZero);
+        assert!(!even_odd.contains(p2(5.0, 5.0)));
+        assert!(non_zero.contains(p2(5.0, 5.0)));
+
+        let strip = Polygon::from_polyline(&rect(-5.0, 4.0, 15.0, 6.0));
+        let union = even_odd.union(&strip);
+        assert_eq!(union.len(), 1);
+        // The strip cuts the hole in two.
+        assert_eq!(union[0].rings.len(), 3);
+        assert!((total_area(&union) - (100.0 - 16.0 + 8.0 + 20.0)).abs() < 1e-3);
+
+        let cut = non_zero.difference(&strip);
+        assert_eq!(cut.len(), 2);
+        assert!((total_area(&cut) - 80.0).abs() < 1e-3);
+
+        let hole = Polygon::from_polyline(&rect(3.0, 3.0, 7.0, 7.0));
+        let ring = non_zero.difference(&hole);
+        assert_eq!(ring.len(), 1);
+        assert_eq!(ring[0].rings.len(), 2);
+        let inner = Polygon::new(vec![ring[0].rings[1].clone()], FillRule::NonZero);
+        assert!((inner.area() + 16.0).abs() < 1e-3);
+        assert!((total_area(&ring) - 84.0).abs() < 1e-3);
+    }
+
+    // The union of a rectangle along each segment of a line through `ps`
+    // that is `w` either side of it.
+    fn outline(ps: &[Point], w: f32) -> Vec<Polygon> {
+        let rings = ps
+            .windows(2)
+            .map(|s| {
+                let d = s[1] - s[0];
+                let o = p2(d.vs[1], -d.vs[0]) * (w / d.norm_squared().sqrt());
+                PolyLine {
+                    ps: vec![s[0] + o, s[1] + o, s[1] - o, s[0] - o],
+                    attributes: (),
+                    closed: true,
+                }
+            })
+            .collect();
+        Polygon::new(rings, FillRule::NonZero).union(&Polygon::new(vec![], FillRule::NonZero))
+    }
+
+    #[test]
+    pub fn nearly_parallel_edges() {
+        // Lines doubling back on themselves leave long edges a hair apart
+        // to be resolved, which could lose the whole area or panic.
+        for (ps, w) in [
+            (
+                [
+                    p2(7.270815, 9.198812),
+                    p2(7.5834045, 2.1666427),
+                    p2(7.0845017, 13.413437),
+                ],
+                0.21273455,
+            ),
+            (
+                [
+                    p2(4.1693506, 0.7081568),
+                    p2(7.8958135, 9.562997),
+                    p2(0.9923191, -6.8408575),
+                ],
+                0.2740091,
+            ),
+        ] {
+            let union = outline(&ps, w);
+            assert_eq!(union.len(), 1);
+            let longest = 2.0 * w * (ps[2] - ps[1]).norm_squared().sqrt();
+            assert!(total_area(&union) > longest - 1e-3);
+        }
+    }
+
+    // Remembers the part of the original line it was sliced from.
+    #[derive(Clone, Debug, Default, PartialEq)]
+    struct Span(Option<((usize, f32), (usize, f32))>);
+
+    impl PolyLineAttribute for Span {
+        type LineAttribute = ();
+        fn attribute_for_line_segment(&self, _index: usize) -> Self::LineAttribute {}
+        fn poly_range(&self, start: (usize, f32), end: (usize, f32)) -> Self {
+            Span(Some((start, end)))
+        }
+    }
+
+    #[test]
+    pub fn clip_to_concave_polygon() {
+        // A U shape, open at the top.
+        let u = Polygon::from_polyline(&PolyLine {
+            ps: vec![
+                p2(0.0, 0.0),
+                p2(10.0, 0.0),
+                p2(10.0, 10.0),
+                p2(7.0, 10.0),
+                p2(7.0, 3.0),
+                p2(3.0, 3.0),
+                p2(3.0, 10.0),
+                p2(0.0, 10.0),
+            ],
+            attributes: (),
+            closed: true,
+        });
+        let line = PolyLine {
+            ps: vec![p2(-5.0, 5.0), p2(5.0, 5.0), p2(15.0, 5.0)],
+            attributes: Span(None),
+            closed: false,
+        };
+        let inside = line.clip_inside(&u);
+        assert_eq!(inside.len(), 2);
+        assert_eq!(inside[0].ps, vec![p2(0.0, 5.0), p2(3.0, 5.0)]);
+        assert_eq!(inside[0].attributes, Span(Some(((0, 0.5), (0, 0.8)))));
+        assert_eq!(inside[1].ps, vec![p2(7.0, 5.0), p2(10.0, 5.0)]);
+        assert_eq!(inside[1].attributes, Span(Some(((1, 0.2), (1, 0.5)))));
+
+        // The middle piece continues through the vertex at (5, 5).
+        let outside = line.clip_outside(&u);
+        assert_eq!(outside.len(), 3);
+        assert_eq!(
+            outside[1].ps,
+            vec![p2(3.0, 5.0), p2(5.0, 5.0), p2(7.0, 5.0)]
+        );
+
+        // A loop reaching down into the bottom of the U is cut in two, with
+        // no seam at its first point.
+        let square = rect(4.0, 1.0, 6.0, 12.0).map_attribute(|_| Span(None));
+        let pieces = square.clip_inside(&u);
+        assert_eq!(pieces.len(), 1);
+        assert!(!pieces[0].closed);
+        assert_eq!(
+            pieces[0].ps,
+            vec![p2(4.0, 3.0), p2(4.0, 1.0), p2(6.0, 1.0), p2(6.0, 3.0)]
+        );
+        // Its range runs from the last segment round to the second.
+        assert_eq!(
+            pieces[0].attributes,
+            Span(Some(((3, 9.0 / 11.0), (1, 2.0 / 11.0))))
+        );
+        let pieces = square.clip_outside(&u);
+        assert_eq!(pieces.len(), 1);
+        assert_eq!(
+            pieces[0].ps,
+            vec![p2(6.0, 3.0), p2(6.0, 12.0), p2(4.0, 12.0), p2(4.0, 3.0)]
+        );
+    }
+}
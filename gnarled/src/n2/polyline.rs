@@ -25,6 +25,7 @@ where
                 })
                 .collect(),
             attributes: self.attributes.clone(),
+            closed: self.closed,
         }
     }
 }
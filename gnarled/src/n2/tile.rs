@@ -8,14 +8,19 @@ pub struct Tile {
     bounds: (Point, Point),
 }
 
-pub fn make_tile(bounds: (Point, Point), lines: &LineSet) -> Tile {
+// Keeps only the parts of the lines inside the axis aligned rectangle.
+pub fn clip_to_rect(lines: &LineSet, bounds: (Point, Point)) -> LineSet {
     let (min, max) = bounds;
+    lines
+        .clip_by(p2(1.0, 0.0), min.vs[0])
+        .clip_by(p2(-1.0, 0.0), -max.vs[0])
+        .clip_by(p2(0.0, 1.0), min.vs[1])
+        .clip_by(p2(0.0, -1.0), -max.vs[1])
+}
+
+pub fn make_tile(bounds: (Point, Point), lines: &LineSet) -> Tile {
     Tile {
-        lines: lines
-            .clip_by(p2(1.0, 0.0), min.vs[0])
-            .clip_by(p2(-1.0, 0.0), -max.vs[0])
-            .clip_by(p2(0.0, 1.0), min.vs[1])
-            .clip_by(p2(0.0, -1.0), -max.vs[1]),
+        lines: clip_to_rect(lines, bounds),
         bounds,
     }
 }
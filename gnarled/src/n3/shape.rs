@@ -47,50 +47,62 @@ impl Shape for AABox {
             PolyLine {
                 ps: vec![p3(x1, y1, z1), p3(x1, y1, z2)],
                 attributes: (),
+                closed: false,
             },
             PolyLine {
                 ps: vec![p3(x1, y1, z1), p3(x1, y2, z1)],
                 attributes: (),
+                closed: false,
             },
             PolyLine {
                 ps: vec![p3(x1, y1, z1), p3(x2, y1, z1)],
                 attributes: (),
+                closed: false,
             },
             PolyLine {
                 ps: vec![p3(x1, y1, z2), p3(x1, y2, z2)],
                 attributes: (),
+                closed: false,
             },
             PolyLine {
                 ps: vec![p3(x1, y1, z2), p3(x2, y1, z2)],
                 attributes: (),
+                closed: false,
             },
             PolyLine {
                 ps: vec![p3(x1, y2, z1), p3(x1, y2, z2)],
                 attributes: (),
+                closed: false,
             },
             PolyLine {
                 ps: vec![p3(x1, y2, z1), p3(x2, y2, z1)],
                 attributes: (),
+                closed: false,
             },
             PolyLine {
                 ps: vec![p3(x1, y2, z2), p3(x2, y2, z2)],
                 attributes: (),
+                closed: false,
             },
             PolyLine {
                 ps: vec![p3(x2, y1, z1), p3(x2, y1, z2)],
                 attributes: (),
+                closed: false,
             },
             PolyLine {
                 ps: vec![p3(x2, y1, z1), p3(x2, y2, z1)],
                 attributes: (),
+                closed: false,
             },
             PolyLine {
                 ps: vec![p3(x2, y1, z2), p3(x2, y2, z2)],
                 attributes: (),
+                closed: false,
             },
             PolyLine {
                 ps: vec![p3(x2, y2, z1), p3(x2, y2, z2)],
                 attributes: (),
+                closed: false,
             },
         ]
     }
@@ -198,11 +210,15 @@ impl Shape for Sphere {
             let xi = 2.0 * xi - 1.0;
             let z = self.center.vs[2] + xi * self.radius;
             let r = (1.0 - xi * xi).sqrt() * self.radius;
-            let ps = (0..=ns)
+            let ps = (0..ns)
                 .map(|i| 2.0 * std::f32::consts::PI * (i as f32) / (ns as f32))
                 .map(|th| self.center + p3(r * th.cos(), r * th.sin(), z))
                 .collect();
-            result.push(PolyLine { ps, attributes: () });
+            result.push(PolyLine {
+                ps,
+                attributes: (),
+                closed: true,
+            });
         }
         result
     }
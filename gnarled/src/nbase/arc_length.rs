@@ -0,0 +1,368 @@
+use crate::nbase::lineset::LineSet;
+use crate::nbase::point::{distance, Point};
+use crate::nbase::polyline::{PolyLine, PolyLineAttribute};
+
+// The distance along the path to each of its points.
+fn cumulative<const N: usize>(ps: &[Point<N>]) -> Vec<f32> {
+    let mut total = 0.0;
+    let mut result = vec![0.0];
+    for w in ps.windows(2) {
+        total += distance(w[0], w[1]);
+        result.push(total);
+    }
+    result
+}
+
+// Where distance `d` along a path falls, as (segment index, parameter
+// along the segment), given the `cumulative` distances of its (at least
+// two) points. Segments with no length are skipped where possible.
+fn locate(cum: &[f32], d: f32) -> (usize, f32) {
+    let segments = cum.len() - 1;
+    let d = d.clamp(0.0, cum[segments]);
+    let mut i = cum[1..].partition_point(|c| *c <= d).min(segments - 1);
+    while cum[i + 1] == cum[i] && i + 1 < segments {
+        i += 1;
+    }
+    while cum[i + 1] == cum[i] && i > 0 {
+        i -= 1;
+    }
+    let l = cum[i + 1] - cum[i];
+    let t = if l > 0.0 {
+        ((d - cum[i]) / l).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    (i, t)
+}
+
+// The points of the path from distance `d0` to `d1 > d0` along it, given
+// its `cumulative` distances, with where those ends fall.
+fn between<const N: usize>(
+    ps: &[Point<N>],
+    cum: &[f32],
+    d0: f32,
+    d1: f32,
+) -> (Vec<Point<N>>, (usize, f32), (usize, f32)) {
+    let (start, end) = (locate(cum, d0), locate(cum, d1));
+    let mut result = vec![Point::lerp(start.1, ps[start.0], ps[start.0 + 1])];
+    result.extend_from_slice(&ps[start.0 + 1..=end.0]);
+    result.push(Point::lerp(end.1, ps[end.0], ps[end.0 + 1]));
+    result.dedup();
+    (result, start, end)
+}
+
+impl<const N: usize, A> PolyLine<N, A> {
+    pub fn length(&self) -> f32 {
+        let ps = self.path_points();
+        cumulative(&ps)[ps.len().max(1) - 1]
+    }
+
+    // Distances past the end of an open line are clamped to it, while a
+    // closed line goes round again.
+    fn wrap(&self, d: f32, length: f32) -> f32 {
+        if self.closed && length > 0.0 {
+            d.rem_euclid(length)
+        } else {
+            d
+        }
+    }
+
+    // The point distance `d` along the line.
+    pub fn point_at(&self, d: f32) -> Option<Point<N>> {
+        let ps = self.path_points();
+        if ps.len() < 2 {
+            return ps.first().cloned();
+        }
+        let cum = cumulative(&ps);
+        let (i, t) = locate(&cum, self.wrap(d, cum[cum.len() - 1]));
+        Some(Point::lerp(t, ps[i], ps[i + 1]))
+    }
+
+    // The unit direction of the line distance `d` along it. At a vertex
+    // this is the direction of the segment leaving it.
+    pub fn tangent_at(&self, d: f32) -> Option<Point<N>> {
+        let ps = self.path_points();
+        if ps.len() < 2 {
+            return None;
+        }
+        let cum = cumulative(&ps);
+        let (i, _) = locate(&cum, self.wrap(d, cum[cum.len() - 1]));
+        let v = ps[i + 1] - ps[i];
+        if v.norm_squared() > 0.0 {
+            Some(v.normalize())
+        } else {
+            None
+        }
+    }
+
+    // The open line from distance `d0` to `d1` along this one, with its
+    // attributes from `poly_range`. None if the range is empty.
+    pub fn sub_path(&self, d0: f32, d1: f32) -> Option<PolyLine<N, A>>
+    where
+        A: PolyLineAttribute,
+    {
+        let ps = self.path_points();
+        if ps.len() < 2 {
+            return None;
+        }
+        let cum = cumulative(&ps);
+        let length = cum[cum.len() - 1];
+        let (d0, d1) = (d0.clamp(0.0, length), d1.clamp(0.0, length));
+        if d1 <= d0 {
+            return None;
+        }
+        let (ps, start, end) = between(&ps, &cum, d0, d1);
+        Some(PolyLine {
+            ps,
+            attributes: self.attributes.poly_range(start, end),
+            closed: false,
+        })
+    }
+
+    // The line through evenly spaced points along this one, no more than
+    // `spacing` apart. The ends are kept, corners in between may not be.
+    pub fn resampled(&self, spacing: f32) -> PolyLine<N, A>
+    where
+        A: Clone,
+    {
+        let ps = self.path_points();
+        let cum = cumulative(&ps);
+        let length = cum[cum.len() - 1];
+        if ps.len() < 2 || length == 0.0 || spacing <= 0.0 {
+            return self.clone();
+        }
+        let n = (length / spacing).ceil() as usize;
+        let mut result: Vec<Point<N>> = (0..=n)
+            .map(|k| {
+                let (i, t) = locate(&cum, length * k as f32 / n as f32);
+                Point::lerp(t, ps[i], ps[i + 1])
+            })
+            .collect();
+        if self.closed {
+            result.pop();
+        }
+        PolyLine {
+            ps: result,
+            attributes: self.attributes.clone(),
+            closed: self.closed,
+        }
+    }
+
+    // The line broken into dashes, as for SVG's stroke-dasharray: on for
+    // `pattern[0]`, off for `pattern[1]` and so on, repeating. An odd
+    // length pattern is repeated to make it even. `phase` is how far into
+    // the pattern the line starts. On a closed line, a dash running over
+    // the end carries on round into the start, and gets its attributes from
+    // a `poly_range` that wraps round too.
+    pub fn dashed(&self, pattern: &[f32], phase: f32) -> Vec<PolyLine<N, A>>
+    where
+        A: PolyLineAttribute + Clone,
+    {
+        let pattern: Vec<f32> = if pattern.len() % 2 == 1 {
+            pattern.iter().chain(pattern.iter()).cloned().collect()
+        } else {
+            pattern.to_vec()
+        };
+        let period: f32 = pattern.iter().sum();
+        if pattern.iter().any(|l| *l < 0.0) || period <= 0.0 {
+            return vec![self.clone()];
+        }
+
+        let ps = self.path_points();
+        if ps.len() < 2 {
+            return vec![];
+        }
+        let cum = cumulative(&ps);
+        let length = cum[cum.len() - 1];
+        // Each dash is placed from the start of its period rather than by
+        // adding up the lengths before it, which would stop advancing once
+        // they are too small to change the total.
+        let offsets: Vec<f32> = pattern
+            .iter()
+            .scan(0.0, |total, l| {
+                let offset = *total;
+                *total += l;
+                Some(offset)
+            })
+            .collect();
+        let phase = phase.rem_euclid(period);
+        let mut dashes = vec![];
+        'periods: for c in 0.. {
+            let base = c as f32 * period - phase;
+            for k in (0..pattern.len()).step_by(2) {
+                let start = base + offsets[k];
+                if start >= length {
+                    break 'periods;
+                }
+                let (d0, d1) = (start.max(0.0), (start + pattern[k]).min(length));
+                if d1 > d0 {
+                    dashes.push((d0, d1));
+                }
+            }
+        }
+
+        let mut result: Vec<PolyLine<N, A>> = dashes
+            .iter()
+            .map(|(d0, d1)| {
+                let (ps, start, end) = between(&ps, &cum, *d0, *d1);
+                PolyLine {
+                    ps,
+                    attributes: self.attributes.poly_range(start, end),
+                    closed: false,
+                }
+            })
+            .collect();
+        let wraps = match (dashes.first(), dashes.last()) {
+            (Some(first), Some(last)) => first.0 == 0.0 && last.1 == length,
+            _ => false,
+        };
+        if self.closed && wraps && dashes.len() > 1 {
+            let first = result.remove(0);
+            let last = result.pop().unwrap();
+            let (start, end) = (
+                locate(&cum, dashes[dashes.len() - 1].0),
+                locate(&cum, dashes[0].1),
+            );
+            let mut ps = last.ps;
+            ps.extend_from_slice(&first.ps[1..]);
+            result.push(PolyLine {
+                ps,
+                attributes: self.attributes.poly_range(start, end),
+                closed: false,
+            });
+        }
+        result
+    }
+}
+
+impl<const N: usize> LineSet<N> {
+    pub fn length(&self) -> f32 {
+        self.lines.iter().map(|l| l.length()).sum()
+    }
+
+    pub fn resampled(&self, spacing: f32) -> LineSet<N> {
+        LineSet {
+            lines: self.lines.iter().map(|l| l.resampled(spacing)).collect(),
+        }
+    }
+
+    // Every line dashed with the same pattern, each starting at `phase`.
+    pub fn dashed(&self, pattern: &[f32], phase: f32) -> LineSet<N> {
+        LineSet {
+            lines: self
+                .lines
+                .iter()
+                .flat_map(|l| l.dashed(pattern, phase))
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::n2::point::p2;
+
+    // Remembers the part of the original line it was taken from.
+    #[derive(Clone, Debug, Default, PartialEq)]
+    struct Span(Option<((usize, f32), (usize, f32))>);
+
+    impl PolyLineAttribute for Span {
+        type LineAttribute = ();
+        fn attribute_for_line_segment(&self, _index: usize) -> Self::LineAttribute {}
+        fn poly_range(&self, start: (usize, f32), end: (usize, f32)) -> Self {
+            Span(Some((start, end)))
+        }
+    }
+
+    fn square(closed: bool) -> PolyLine<2, ()> {
+        PolyLine {
+            ps: vec![p2(0.0, 0.0), p2(10.0, 0.0), p2(10.0, 10.0), p2(0.0, 10.0)],
+            attributes: (),
+            closed,
+        }
+    }
+
+    #[test]
+    pub fn arc_length_queries() {
+        let open = square(false);
+        let closed = square(true);
+        assert_eq!(open.length(), 30.0);
+        assert_eq!(closed.length(), 40.0);
+        assert_eq!(open.point_at(15.0).unwrap().vs, [10.0, 5.0]);
+        assert_eq!(open.point_at(45.0).unwrap().vs, [0.0, 10.0]);
+        assert_eq!(closed.point_at(45.0).unwrap().vs, [5.0, 0.0]);
+        assert_eq!(open.tangent_at(10.0).unwrap().vs, [0.0, 1.0]);
+        assert_eq!(closed.tangent_at(35.0).unwrap().vs, [0.0, -1.0]);
+
+        let sub = closed.sub_path(5.0, 35.0).unwrap();
+        assert_eq!(
+            sub.ps,
+            vec![
+                p2(5.0, 0.0),
+                p2(10.0, 0.0),
+                p2(10.0, 10.0),
+                p2(0.0, 10.0),
+                p2(0.0, 5.0)
+            ]
+        );
+        assert!(!sub.closed);
+        assert!(open.sub_path(20.0, 10.0).is_none());
+
+        let resampled = closed.resampled(3.0);
+        assert_eq!(resampled.ps.len(), 14);
+        assert!(resampled.closed);
+        // Corners are cut, but only a little.
+        assert!(resampled.length() <= 40.0 && resampled.length() > 37.0);
+    }
+
+    #[test]
+    pub fn dash_with_phase() {
+        let lines = LineSet {
+            lines: vec![PolyLine {
+                ps: vec![p2(0.0, 0.0), p2(10.0, 0.0)],
+                attributes: (),
+                closed: false,
+            }],
+        };
+        let dashes = lines.dashed(&[2.0, 1.0], 0.0);
+        assert_eq!(dashes.lines.len(), 4);
+        assert_eq!(dashes.lines[1].ps, vec![p2(3.0, 0.0), p2(5.0, 0.0)]);
+        assert_eq!(dashes.lines[3].ps, vec![p2(9.0, 0.0), p2(10.0, 0.0)]);
+
+        // Starting half way through the first dash.
+        let dashes = lines.dashed(&[2.0, 1.0], 1.0);
+        assert_eq!(dashes.lines[0].ps, vec![p2(0.0, 0.0), p2(1.0, 0.0)]);
+        assert_eq!(dashes.lines[1].ps, vec![p2(2.0, 0.0), p2(4.0, 0.0)]);
+
+        // An odd pattern alternates which entries are dashes.
+        let dashes = lines.dashed(&[1.0], 0.0);
+        assert_eq!(dashes.lines.len(), 5);
+        assert!((dashes.length() - 5.0).abs() < 1e-5);
+
+        // Dashes follow a path round its corners.
+        let dashes = square(false).dashed(&[12.0, 100.0], 5.0);
+        assert_eq!(dashes.len(), 1);
+        assert_eq!(dashes[0].ps, vec![p2(0.0, 0.0), p2(7.0, 0.0)]);
+        let dashes = square(true).dashed(&[14.0, 6.0], 0.0);
+        assert_eq!(dashes.len(), 2);
+        assert_eq!(
+            dashes[1].ps,
+            vec![p2(10.0, 10.0), p2(0.0, 10.0), p2(0.0, 6.0)]
+        );
+
+        // A dash over the start of a closed line isn't split there.
+        let dashes = square(true).dashed(&[12.0, 4.0], 0.0);
+        assert_eq!(dashes.len(), 2);
+        assert_eq!(
+            dashes[1].ps,
+            vec![p2(0.0, 8.0), p2(0.0, 0.0), p2(10.0, 0.0), p2(10.0, 2.0)]
+        );
+        let dashes = square(true)
+            .map_attribute(|_| Span(None))
+            .dashed(&[12.0, 4.0], 0.0);
+        assert_eq!(dashes[1].attributes, Span(Some(((3, 0.2), (1, 0.2)))));
+        // But it is on an open one.
+        assert_eq!(square(false).dashed(&[12.0, 4.0], 2.0).len(), 2);
+    }
+}
@@ -1,4 +1,8 @@
+use crate::attributes::AttributeRange;
+use crate::nbase::line_segment::LineSegment;
+use crate::nbase::lineset::LineSet;
 use crate::nbase::point::Point;
+use crate::nbase::polyline::PolyLine;
 
 use crate::nbase::point::Float;
 
@@ -26,4 +30,173 @@ impl<const N: usize> Bounds<N> {
         }
         true
     }
+
+    // The range of t for which a + t (b - a) is inside, using Liang-Barsky.
+    // Returns None if the segment from a to b misses, or only touches.
+    pub fn clip_parameters(&self, a: Point<N>, b: Point<N>) -> Option<(f32, f32)> {
+        let d = b - a;
+        let (mut t0, mut t1) = (0.0f32, 1.0f32);
+        for i in 0..N {
+            // Inside the boundary when p t <= q.
+            for (p, q) in [
+                (-d.vs[i], a.vs[i] - self.min.vs[i]),
+                (d.vs[i], self.max.vs[i] - a.vs[i]),
+            ] {
+                if p == 0.0 {
+                    if q < 0.0 {
+                        return None;
+                    }
+                } else if p < 0.0 {
+                    t0 = t0.max(q / p);
+                } else {
+                    t1 = t1.min(q / p);
+                }
+            }
+        }
+        if t0 < t1 {
+            Some((t0, t1))
+        } else {
+            None
+        }
+    }
+
+    pub fn clip<A>(&self, ls: LineSegment<N, A>) -> Option<LineSegment<N, A>>
+    where
+        A: AttributeRange,
+    {
+        let [a, b] = ls.ps;
+        let (t0, t1) = self.clip_parameters(a, b)?;
+        Some(LineSegment {
+            ps: [Point::lerp(t0, a, b), Point::lerp(t1, a, b)],
+            attributes: ls.attributes.range(t0, t1),
+        })
+    }
+
+    // The parts of the line inside the bounds. For the attributes the line
+    // is parameterised by (segment index + t) / number of segments, and a
+    // closed line that is cut is joined back up at its first point.
+    pub fn clip_polyline<A>(&self, pl: &PolyLine<N, A>) -> Vec<PolyLine<N, A>>
+    where
+        A: AttributeRange,
+    {
+        let ps = pl.path_points();
+        if ps.len() < 2 {
+            return vec![];
+        }
+        let n = (ps.len() - 1) as f32;
+        // (start, points, end) of each piece, as parameters along the line.
+        let mut pieces: Vec<(f32, Vec<Point<N>>, f32)> = vec![];
+        for (i, w) in ps.windows(2).enumerate() {
+            let (t0, t1) = match self.clip_parameters(w[0], w[1]) {
+                Some(ts) => ts,
+                None => continue,
+            };
+            let (u0, u1) = ((i as f32 + t0) / n, (i as f32 + t1) / n);
+            let p1 = Point::lerp(t1, w[0], w[1]);
+            match pieces.last_mut() {
+                Some((_, ps, end)) if *end == u0 => {
+                    ps.push(p1);
+                    *end = u1;
+                }
+                _ => pieces.push((u0, vec![Point::lerp(t0, w[0], w[1]), p1], u1)),
+            }
+        }
+
+        if pl.closed && !pieces.is_empty() && pieces[0].0 == 0.0 && pieces.last().unwrap().2 == 1.0
+        {
+            if pieces.len() == 1 {
+                return vec![PolyLine {
+                    ps: pl.ps.clone(),
+                    attributes: pl.attributes.range(0.0, 1.0),
+                    closed: true,
+                }];
+            }
+            let (_, first, first_end) = pieces.remove(0);
+            let last = pieces.last_mut().unwrap();
+            last.1.extend_from_slice(&first[1..]);
+            last.2 = first_end;
+        }
+
+        pieces
+            .into_iter()
+            .map(|(start, ps, end)| PolyLine {
+                ps,
+                attributes: pl.attributes.range(start, end),
+                closed: false,
+            })
+            .collect()
+    }
+
+    pub fn clip_lineset(&self, lines: &LineSet<N>) -> LineSet<N> {
+        LineSet {
+            lines: lines
+                .lines
+                .iter()
+                .flat_map(|line| self.clip_polyline(line))
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::n2::point::p2;
+    use crate::n3::p3;
+
+    // Remembers the part of the original line it was cut from.
+    #[derive(Clone, Debug, PartialEq)]
+    struct Span(f32, f32);
+
+    impl AttributeRange for Span {
+        fn range(&self, t0: f32, t1: f32) -> Self {
+            Span(t0, t1)
+        }
+    }
+
+    #[test]
+    pub fn clip_segments_and_polylines() {
+        let cube = Bounds {
+            min: p3(0.0, 0.0, 0.0),
+            max: p3(1.0, 1.0, 1.0),
+        };
+        let ls = LineSegment {
+            ps: [p3(-1.0, 0.5, 0.5), p3(3.0, 0.5, 0.5)],
+            attributes: Span(0.0, 1.0),
+        };
+        let clipped = cube.clip(ls).unwrap();
+        assert_eq!(clipped.ps, [p3(0.0, 0.5, 0.5), p3(1.0, 0.5, 0.5)]);
+        assert_eq!(clipped.attributes, Span(0.25, 0.5));
+        let miss = LineSegment {
+            ps: [p3(-1.0, 0.5, 2.0), p3(3.0, 0.5, 2.0)],
+            attributes: Span(0.0, 1.0),
+        };
+        assert!(cube.clip(miss).is_none());
+
+        let square = Bounds {
+            min: p2(0.0, 0.0),
+            max: p2(10.0, 10.0),
+        };
+        // In, out over the top, and back in again.
+        let pl = PolyLine {
+            ps: vec![p2(5.0, 5.0), p2(5.0, 15.0), p2(8.0, 5.0)],
+            attributes: Span(0.0, 1.0),
+            closed: false,
+        };
+        let pieces = square.clip_polyline(&pl);
+        assert_eq!(pieces.len(), 2);
+        assert_eq!(pieces[0].ps, vec![p2(5.0, 5.0), p2(5.0, 10.0)]);
+        assert_eq!(pieces[0].attributes, Span(0.0, 0.25));
+        assert_eq!(pieces[1].ps, vec![p2(6.5, 10.0), p2(8.0, 5.0)]);
+        assert_eq!(pieces[1].attributes, Span(0.75, 1.0));
+
+        // Closing it makes the last piece carry on into the first.
+        let pieces = square.clip_polyline(&PolyLine { closed: true, ..pl });
+        assert_eq!(pieces.len(), 1);
+        assert_eq!(
+            pieces[0].ps,
+            vec![p2(6.5, 10.0), p2(8.0, 5.0), p2(5.0, 5.0), p2(5.0, 10.0)]
+        );
+        assert_eq!(pieces[0].attributes, Span(0.5, 1.0 / 6.0));
+    }
 }
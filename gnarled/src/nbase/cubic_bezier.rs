@@ -1,5 +1,8 @@
-use crate::nbase::point::Point;
-use crate::nbase::traits::Shiftable;
+use crate::nbase::bounds::Bounds;
+use crate::nbase::lineset::LineSet;
+use crate::nbase::point::{distance, Point};
+use crate::nbase::polyline::PolyLine;
+use crate::nbase::traits::{Boundable, Shiftable};
 
 use super::point::Float;
 
@@ -52,6 +55,278 @@ where
         let db = cubic_basis_deriv(t);
         self.ps[0] * db[0] + self.ps[1] * db[1] + self.ps[2] * db[2] + self.ps[3] * db[3]
     }
+
+    // The curve lies inside the hull of its control points, so it is
+    // no further from the chord than the inner two are.
+    fn flatness(&self) -> F {
+        let (a, b) = (self.ps[0], self.ps[3]);
+        let d = b - a;
+        let l2 = d.dot(d);
+        let off_chord = |p: Point<N, F>| {
+            if l2 > F::zero() {
+                let t = ((p - a).dot(d) / l2).clamp(F::zero(), F::one());
+                distance(p, a + d * t)
+            } else {
+                distance(p, a)
+            }
+        };
+        F::max(off_chord(self.ps[1]), off_chord(self.ps[2]))
+    }
+
+    // Points along the curve, after its start, such that the lines
+    // through them are within `tolerance` of it. Tolerances too fine for
+    // `F` to tell apart at the curve's size, including zero or negative
+    // ones, are raised to that, which is a millionth of it for f32.
+    fn flatten_into(&self, tolerance: F, out: &mut Vec<Point<N, F>>) {
+        let half = F::from_f64(0.5);
+        let size = self.ps[1..]
+            .iter()
+            .map(|p| distance(*p, self.ps[0]))
+            .fold(F::zero(), F::max);
+        let finest = F::max(F::from_f64(8.0) * F::epsilon(), F::from_f64(1e-10)) * size;
+        let tolerance = if tolerance > finest {
+            tolerance
+        } else {
+            finest
+        };
+        // Each split cuts the distance from the chord to about a quarter,
+        // so this is only reached for huge or bad input.
+        let max_depth = 24;
+        let mut stack = vec![(self.clone(), 0)];
+        while let Some((segment, depth)) = stack.pop() {
+            if depth >= max_depth || segment.flatness() <= tolerance {
+                out.push(segment.ps[3]);
+            } else {
+                let (first, second) = segment.split(half);
+                stack.push((second, depth + 1));
+                stack.push((first, depth + 1));
+            }
+        }
+    }
+
+    // The curve as points joined by lines that are everywhere within
+    // `tolerance` of it, with fewer points where it is straighter.
+    pub fn flatten(&self, tolerance: F) -> Vec<Point<N, F>> {
+        let mut ps = vec![self.ps[0]];
+        self.flatten_into(tolerance, &mut ps);
+        ps
+    }
+}
+
+// Five point Gauss-Legendre quadrature, as (node on [-1, 1], weight).
+const GAUSS_LEGENDRE: [(f64, f64); 5] = [
+    (0.0, 0.5688888888888889),
+    (-0.5384693101056831, 0.4786286704993665),
+    (0.5384693101056831, 0.4786286704993665),
+    (-0.906179845938664, 0.2369268850561891),
+    (0.906179845938664, 0.2369268850561891),
+];
+
+// The roots of a t^2 + b t + c strictly between 0 and 1. Written so that
+// nearly linear quadratics don't lose the root that matters.
+fn unit_quadratic_roots<F: Float>(a: F, b: F, c: F) -> Vec<F> {
+    let disc = b * b - F::from_f64(4.0) * a * c;
+    if disc < F::zero() || (a == F::zero() && b == F::zero()) {
+        return vec![];
+    }
+    let sign = if b < F::zero() { -F::one() } else { F::one() };
+    let q = -F::from_f64(0.5) * (b + sign * disc.sqrt());
+    let mut roots = vec![];
+    if a != F::zero() {
+        roots.push(q / a);
+    }
+    if q != F::zero() {
+        roots.push(c / q);
+    }
+    roots.retain(|t| *t > F::zero() && *t < F::one());
+    roots
+}
+
+impl<const N: usize, F> CubicBezierSegment<N, F>
+where
+    F: Float,
+{
+    pub fn second_derivative(&self, t: F) -> Point<N, F> {
+        let c6 = F::from_f64(6.0);
+        let c2 = F::from_f64(2.0);
+        let a = self.ps[2] - self.ps[1] * c2 + self.ps[0];
+        let b = self.ps[3] - self.ps[2] * c2 + self.ps[1];
+        (a * (F::one() - t) + b * t) * c6
+    }
+
+    fn speed(&self, t: F) -> F {
+        self.derivative(t).norm_squared().sqrt()
+    }
+
+    fn gauss_length(&self, t0: F, t1: F) -> F {
+        let half = F::from_f64(0.5) * (t1 - t0);
+        let mid = F::from_f64(0.5) * (t0 + t1);
+        GAUSS_LEGENDRE
+            .iter()
+            .map(|(x, w)| F::from_f64(*w) * self.speed(mid + half * F::from_f64(*x)))
+            .sum::<F>()
+            * half
+    }
+
+    // The length of the curve between parameters `t0` and `t1`, splitting
+    // the range until the quadrature agrees with itself to within a few
+    // rounding errors.
+    pub fn length_between(&self, t0: F, t1: F) -> F {
+        if self.ps.iter().all(|p| *p == self.ps[0]) {
+            return F::zero();
+        }
+        let tolerance = F::from_f64(16.0) * F::epsilon();
+        let max_depth = 10;
+        let mut total = F::zero();
+        let mut stack = vec![(t0, t1, self.gauss_length(t0, t1), 0)];
+        while let Some((a, b, whole, depth)) = stack.pop() {
+            let m = F::from_f64(0.5) * (a + b);
+            let (left, right) = (self.gauss_length(a, m), self.gauss_length(m, b));
+            if depth >= max_depth || (left + right - whole).abs() <= tolerance * whole.abs() {
+                total += left + right;
+            } else {
+                stack.push((a, m, left, depth + 1));
+                stack.push((m, b, right, depth + 1));
+            }
+        }
+        total
+    }
+
+    pub fn length(&self) -> F {
+        self.length_between(F::zero(), F::one())
+    }
+
+    // The parameter distance `s` along the curve, clamped to its ends.
+    pub fn t_at_length(&self, s: F) -> F {
+        let length = self.length();
+        if s <= F::zero() || length == F::zero() {
+            return F::zero();
+        }
+        if s >= length {
+            return F::one();
+        }
+        // Newton's method, falling back to bisection if it leaves the
+        // range the answer is known to be in.
+        let (mut lo, mut hi) = (F::zero(), F::one());
+        let mut t = s / length;
+        for _ in 0..32 {
+            let error = self.length_between(F::zero(), t) - s;
+            if error.abs() <= F::from_f64(1e-6) * length {
+                break;
+            }
+            if error > F::zero() {
+                hi = t;
+            } else {
+                lo = t;
+            }
+            let speed = self.speed(t);
+            let next = if speed > F::zero() {
+                t - error / speed
+            } else {
+                lo
+            };
+            t = if next > lo && next < hi {
+                next
+            } else {
+                F::from_f64(0.5) * (lo + hi)
+            };
+        }
+        t
+    }
+
+    // The smallest box containing the curve, which is usually well inside
+    // the one around the control points.
+    pub fn tight_bounds(&self) -> Bounds<N, F> {
+        let c2 = F::from_f64(2.0);
+        let (d0, d1, d2) = (
+            self.ps[1] - self.ps[0],
+            self.ps[2] - self.ps[1],
+            self.ps[3] - self.ps[2],
+        );
+        let mut bounds = Bounds {
+            min: Point::componentwise_min(self.ps[0], self.ps[3]),
+            max: Point::componentwise_max(self.ps[0], self.ps[3]),
+        };
+        // Each coordinate's derivative is a quadratic in t.
+        for i in 0..N {
+            let a = d0.vs[i] - c2 * d1.vs[i] + d2.vs[i];
+            let b = c2 * (d1.vs[i] - d0.vs[i]);
+            for t in unit_quadratic_roots(a, b, d0.vs[i]) {
+                let v = self.value(t).vs[i];
+                bounds.min.vs[i] = F::min(bounds.min.vs[i], v);
+                bounds.max.vs[i] = F::max(bounds.max.vs[i], v);
+            }
+        }
+        bounds
+    }
+
+    // The parameter of the point on the curve closest to `p`, and that
+    // point. The curve is sampled to find roughly where, then Newton's
+    // method homes in on it.
+    pub fn nearest_point(&self, p: Point<N, F>) -> (F, Point<N, F>) {
+        let samples = 16;
+        let distance2 = |t: F| (self.value(t) - p).norm_squared();
+        let mut best = F::zero();
+        for i in 1..=samples {
+            let t = F::from_f64(i as f64 / samples as f64);
+            if distance2(t) < distance2(best) {
+                best = t;
+            }
+        }
+        let mut t = best;
+        for _ in 0..8 {
+            let (v, d, dd) = (
+                self.value(t) - p,
+                self.derivative(t),
+                self.second_derivative(t),
+            );
+            // Where (B(t) - p) . B'(t) is zero.
+            let slope = d.norm_squared() + v.dot(dd);
+            if slope <= F::zero() {
+                break;
+            }
+            let next = (t - v.dot(d) / slope).clamp(F::zero(), F::one());
+            if distance2(next) > distance2(t) {
+                break;
+            }
+            t = next;
+        }
+        (t, self.value(t))
+    }
+
+    // One over the radius of the circle that best fits the curve at `t`.
+    pub fn curvature(&self, t: F) -> F {
+        let (d, dd) = (self.derivative(t), self.second_derivative(t));
+        let speed2 = d.norm_squared();
+        if speed2 == F::zero() {
+            return F::zero();
+        }
+        let ddd = d.dot(dd);
+        // |d x dd| without needing the cross product, so any N works.
+        let area2 = F::max(speed2 * dd.norm_squared() - ddd * ddd, F::zero());
+        area2.sqrt() / (speed2 * speed2.sqrt())
+    }
+}
+
+impl<F> CubicBezierSegment<2, F>
+where
+    F: Float,
+{
+    // The parameters strictly between the ends where the curve changes
+    // the way it bends, in increasing order. A cusp counts once.
+    pub fn inflections(&self) -> Vec<F> {
+        let cross = |u: Point<2, F>, v: Point<2, F>| u.vs[0] * v.vs[1] - u.vs[1] * v.vs[0];
+        let c2 = F::from_f64(2.0);
+        let c3 = F::from_f64(3.0);
+        // B'(t) is a multiple of a + 2 b t + c t^2, and B''(t) of b + c t.
+        let a = self.ps[1] - self.ps[0];
+        let b = self.ps[2] - self.ps[1] * c2 + self.ps[0];
+        let c = self.ps[3] - self.ps[2] * c3 + self.ps[1] * c3 - self.ps[0];
+        let mut ts = unit_quadratic_roots(cross(b, c), cross(a, c), cross(a, b));
+        ts.sort_by(|x, y| x.partial_cmp(y).unwrap());
+        ts.dedup();
+        ts
+    }
 }
 
 #[inline]
@@ -97,6 +372,57 @@ impl<const N: usize> CubicBezierPath<N> {
     }
 }
 
+impl<const N: usize> CubicBezierPath<N> {
+    // The path as a polyline within `tolerance` of it. A path that ends
+    // where it started gives a closed line. Any points after the last
+    // whole segment are ignored.
+    pub fn flatten(&self, tolerance: f32) -> PolyLine<N, ()> {
+        let mut ps: Vec<Point<N>> = self.ps.iter().take(1).cloned().collect();
+        for n in 0..self.ps.len().saturating_sub(1) / 3 {
+            self.segment(n).flatten_into(tolerance, &mut ps);
+        }
+        let closed = ps.len() > 2 && ps[0] == ps[ps.len() - 1];
+        if closed {
+            ps.pop();
+        }
+        PolyLine {
+            ps,
+            attributes: (),
+            closed,
+        }
+    }
+}
+
+impl<const N: usize> LineSet<N> {
+    // Flattens each of the paths, see `CubicBezierPath::flatten`.
+    pub fn from_bezier_paths(paths: &[CubicBezierPath<N>], tolerance: f32) -> LineSet<N> {
+        LineSet {
+            lines: paths.iter().map(|p| p.flatten(tolerance)).collect(),
+        }
+    }
+}
+
+impl<const N: usize> Boundable<N> for CubicBezierSegment<N, f32> {
+    fn bounds(&self) -> Option<Bounds<N>> {
+        Some(self.tight_bounds())
+    }
+}
+
+impl<const N: usize> Boundable<N> for CubicBezierPath<N> {
+    fn bounds(&self) -> Option<Bounds<N>> {
+        if self.ps.len() < 4 {
+            return self
+                .ps
+                .iter()
+                .fold(None, crate::nbase::point::point_extrema);
+        }
+        (0..(self.ps.len() - 1) / 3)
+            .map(|n| self.segment(n).tight_bounds())
+            .flat_map(|b| [b.min, b.max])
+            .fold(None, |b, p| crate::nbase::point::point_extrema(b, &p))
+    }
+}
+
 impl<const N: usize> Shiftable<N> for CubicBezierPath<N> {
     type Result = CubicBezierPath<N>;
 
@@ -116,3 +442,146 @@ impl<const N: usize> Shiftable<N> for CubicBezierSegment<N, f32> {
         }
     }
 }
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::n2::point::p2;
+
+    #[test]
+    pub fn flatten_within_tolerance() {
+        // Roughly a quarter circle of radius 10.
+        let k = 10.0 * 0.5523;
+        let segment = CubicBezierSegment {
+            ps: [p2(10.0, 0.0), p2(10.0, k), p2(k, 10.0), p2(0.0, 10.0)],
+        };
+        let mut last = 0;
+        for tolerance in [1.0, 0.1, 0.01, 0.001] {
+            let ps = segment.flatten(tolerance);
+            assert!(ps.len() > last);
+            last = ps.len();
+            assert_eq!(ps[0].vs, [10.0, 0.0]);
+            assert_eq!(ps[ps.len() - 1].vs, [0.0, 10.0]);
+            // The middle of each chord is near the circle.
+            for w in ps.windows(2) {
+                let m = Point::lerp(0.5, w[0], w[1]);
+                assert!(10.0 - m.norm_squared().sqrt() < tolerance + 0.01);
+            }
+        }
+
+        // Straight segments need no extra points.
+        let straight = CubicBezierSegment {
+            ps: [p2(0.0, 0.0), p2(1.0, 0.0), p2(2.0, 0.0), p2(3.0, 0.0)],
+        };
+        assert_eq!(straight.flatten(0.01).len(), 2);
+
+        // Four quarters make a closed circle.
+        let circle = CubicBezierPath {
+            ps: vec![
+                p2(10.0, 0.0),
+                p2(10.0, k),
+                p2(k, 10.0),
+                p2(0.0, 10.0),
+                p2(-k, 10.0),
+                p2(-10.0, k),
+                p2(-10.0, 0.0),
+                p2(-10.0, -k),
+                p2(-k, -10.0),
+                p2(0.0, -10.0),
+                p2(k, -10.0),
+                p2(10.0, -k),
+                p2(10.0, 0.0),
+            ],
+        };
+        let lines = LineSet::from_bezier_paths(&[circle], 0.05);
+        assert_eq!(lines.lines.len(), 1);
+        assert!(lines.lines[0].closed);
+        assert!((lines.lines[0].length() - 20.0 * std::f32::consts::PI).abs() < 0.5);
+
+        // A stray point after the last segment is dropped, and a tolerance
+        // of zero still gives a sensible number of points.
+        let mut stray = segment.ps.to_vec();
+        stray.push(p2(5.0, 5.0));
+        let line = CubicBezierPath { ps: stray }.flatten(0.0);
+        assert_eq!(line.ps[line.ps.len() - 1].vs, [0.0, 10.0]);
+        assert!(line.ps.len() > 100 && line.ps.len() <= 1025);
+        assert!(
+            CubicBezierPath {
+                ps: vec![p2(1.0, 1.0); 3]
+            }
+            .flatten(0.1)
+            .ps
+            .len()
+                == 1
+        );
+    }
+
+    #[test]
+    pub fn bezier_analysis() {
+        // An S curve, in f64.
+        let s = CubicBezierSegment {
+            ps: [
+                Point::<2, f64>::from([0.0, 0.0]),
+                Point::from([1.0, 2.0]),
+                Point::from([2.0, -2.0]),
+                Point::from([3.0, 0.0]),
+            ],
+        };
+        let bounds = s.tight_bounds();
+        assert_eq!(bounds.min.vs[0], 0.0);
+        assert_eq!(bounds.max.vs[0], 3.0);
+        // y = 6t(1-t)(1-2t), which peaks at t = (3 - sqrt 3)/6.
+        let t = (3.0 - 3f64.sqrt()) / 6.0;
+        let peak = 6.0 * t * (1.0 - t) * (1.0 - 2.0 * t);
+        assert!((bounds.max.vs[1] - peak).abs() < 1e-12);
+        assert!((bounds.min.vs[1] + peak).abs() < 1e-12);
+
+        let inflections = s.inflections();
+        assert_eq!(inflections.len(), 1);
+        assert!((inflections[0] - 0.5).abs() < 1e-12);
+        assert!(s.curvature(0.5) < 1e-12);
+        assert!(s.curvature(0.2) > 0.1);
+        let cusp = CubicBezierSegment {
+            ps: [
+                Point::<2, f64>::from([0.0, 0.0]),
+                Point::from([3.0, 3.0]),
+                Point::from([0.0, 3.0]),
+                Point::from([3.0, 0.0]),
+            ],
+        };
+        assert_eq!(cusp.inflections(), vec![0.5]);
+
+        // Compare against a finely flattened curve.
+        let flat: f64 = s
+            .flatten(1e-9)
+            .windows(2)
+            .map(|w| distance(w[0], w[1]))
+            .sum();
+        assert!((s.length() - flat).abs() < 1e-6);
+        let half = s.t_at_length(0.5 * s.length());
+        assert!((half - 0.5).abs() < 1e-6);
+        let t = s.t_at_length(1.0);
+        assert!((s.length_between(0.0, t) - 1.0).abs() < 1e-5);
+
+        // Off to the side of the middle, which heads along (1, -1).
+        let (t, p) = s.nearest_point(Point::from([1.7, 0.2]));
+        assert!((t - 0.5).abs() < 1e-9);
+        assert!(distance(p, Point::from([1.5, 0.0])) < 1e-9);
+        let (t, _) = s.nearest_point(Point::from([-1.0, -1.0]));
+        assert_eq!(t, 0.0);
+
+        // The f32 path bounds are those of its segments.
+        let k = 0.5523;
+        let arc = CubicBezierPath {
+            ps: vec![p2(1.0, 0.0), p2(1.0, k), p2(k, 1.0), p2(0.0, 1.0)],
+        };
+        let bounds = arc.bounds().unwrap();
+        assert_eq!(bounds.max.vs, [1.0, 1.0]);
+        assert!((arc.segment(0).curvature(0.5) - 1.0).abs() < 0.01);
+        assert!((arc.segment(0).length() - std::f32::consts::FRAC_PI_2).abs() < 1e-3);
+        let point = CubicBezierSegment {
+            ps: [p2(1e6, 1e6); 4],
+        };
+        assert_eq!(point.length(), 0.0);
+    }
+}
@@ -68,15 +68,26 @@ fn merge_pl<const N: usize>(
     pl1: &PolyLine<N, ()>,
     pl2: &PolyLine<N, ()>,
 ) -> Option<PolyLine<N, ()>> {
+    if pl1.closed || pl2.closed {
+        return None;
+    }
     let z = *pl1.ps.last().unwrap() - *pl2.ps.first().unwrap();
     let z2 = z.dot(z);
     if z2 > 0.01 {
         return None;
     }
 
+    let mut ps: Vec<_> = pl1.ps.iter().chain(pl2.ps[1..].iter()).cloned().collect();
+    // If the ends now meet we have a loop, which can't be extended any further.
+    let z = ps[0] - *ps.last().unwrap();
+    let closed = ps.len() > 3 && z.dot(z) <= 0.01;
+    if closed {
+        ps.pop();
+    }
     return Some(PolyLine {
-        ps: pl1.ps.iter().chain(pl2.ps[1..].iter()).cloned().collect(),
+        ps,
         attributes: (),
+        closed,
     });
 }
 
@@ -594,6 +605,7 @@ impl<const N: usize> BinningPolyLineMerger<N> {
             let mut pl = PolyLine {
                 ps: vec![ls.ps[0], ls.ps[1]],
                 attributes: (),
+                closed: false,
             };
 
             // Find the bin for the start vertex
@@ -652,7 +664,12 @@ impl<const N: usize> BinningPolyLineMerger<N> {
             }
 
             let idx = self.entries.len();
+            let closed = pl.closed;
             self.entries.push(Some(pl));
+            // Nothing can be joined onto a closed loop.
+            if closed {
+                continue;
+            }
             self.nodes
                 .entry(start_idx)
                 .or_default()
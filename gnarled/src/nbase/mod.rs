@@ -1,8 +1,14 @@
+pub mod arc_length;
 pub mod bounds;
 pub mod cubic_bezier;
 pub mod line_merger;
 pub mod line_segment;
 pub mod lineset;
+pub mod path_order;
+pub mod plot_job;
+pub mod plot_stats;
 pub mod point;
 pub mod polyline;
+pub mod simplify;
+pub mod smooth;
 pub mod traits;
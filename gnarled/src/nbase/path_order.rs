@@ -0,0 +1,548 @@
+use std::collections::HashMap;
+
+use tokio::sync::mpsc::{Receiver, Sender};
+
+use crate::attributes::AttributeReverse;
+use crate::nbase::lineset::LineSet;
+use crate::nbase::point::{distance, point_extrema, Point};
+use crate::nbase::polyline::PolyLine;
+
+#[derive(Clone, Copy, Debug)]
+pub struct PathOrderOptions {
+    // Whether paths may be drawn end-to-start.
+    pub allow_reverse: bool,
+    // Maximum number of 2-opt improvement passes. Only used when
+    // `allow_reverse` is set.
+    pub two_opt_passes: usize,
+    // How many of the nearest paths to each path 2-opt tries joining it
+    // to, which keeps each pass roughly linear in the number of paths.
+    pub neighbours: usize,
+}
+
+impl Default for PathOrderOptions {
+    fn default() -> Self {
+        PathOrderOptions {
+            allow_reverse: true,
+            two_opt_passes: 10,
+            neighbours: 8,
+        }
+    }
+}
+
+// Pen-up travel distance before and after reordering.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PathOrderReport {
+    pub travel_before: f32,
+    pub travel_after: f32,
+}
+
+#[derive(Clone, Copy)]
+struct OrientedPath<const N: usize> {
+    index: usize,
+    reversed: bool,
+    start: Point<N>,
+    end: Point<N>,
+}
+
+impl<const N: usize> OrientedPath<N> {
+    fn flip(&self) -> OrientedPath<N> {
+        OrientedPath {
+            index: self.index,
+            reversed: !self.reversed,
+            start: self.end,
+            end: self.start,
+        }
+    }
+}
+
+// Total pen-up distance to draw `lines` in order, starting at `start`.
+pub fn travel_distance<const N: usize, A>(start: Point<N>, lines: &[PolyLine<N, A>]) -> f32 {
+    let mut current = start;
+    let mut total = 0.0;
+    for line in lines {
+        if let (Some(first), Some(last)) = (line.ps.first(), line.ps.last()) {
+            total += distance(current, *first);
+            // A closed line finishes where it started.
+            current = if line.closed { *first } else { *last };
+        }
+    }
+    total
+}
+
+fn oriented_travel<const N: usize>(start: Point<N>, order: &[OrientedPath<N>]) -> f32 {
+    let mut current = start;
+    let mut total = 0.0;
+    for p in order {
+        total += distance(current, p.start);
+        current = p.end;
+    }
+    total
+}
+
+// The ends of paths bucketed into cells, so those nearest a point can be
+// found by looking outwards from its cell rather than at every path.
+struct EndGrid<const N: usize> {
+    min: Point<N>,
+    cell: f32,
+    // Cells along each axis, when the grid was built.
+    size: i64,
+    cells: HashMap<[i64; N], Vec<(usize, bool)>>,
+    len: usize,
+}
+
+impl<const N: usize> EndGrid<N> {
+    // Each end is (path index, whether it is the end rather than the
+    // start, where it is).
+    fn new(ends: &[(usize, bool, Point<N>)]) -> EndGrid<N> {
+        let bounds = ends
+            .iter()
+            .map(|e| e.2)
+            .fold(None, |b, p| point_extrema(b, &p));
+        let min = bounds.map(|b| b.min).unwrap_or_default();
+        let extent = bounds.map(|b| (b.max - b.min).max()).unwrap_or(0.0);
+        // Around one end per cell when they are spread out evenly.
+        let size = (ends.len() as f32).powf(1.0 / N as f32).ceil().max(1.0);
+        let cell = if extent > 0.0 { extent / size } else { 1.0 };
+        let mut grid = EndGrid {
+            min,
+            cell,
+            size: size as i64,
+            cells: HashMap::new(),
+            len: ends.len(),
+        };
+        for (index, is_end, p) in ends {
+            grid.cells
+                .entry(grid.cell_of(*p))
+                .or_default()
+                .push((*index, *is_end));
+        }
+        grid
+    }
+
+    fn cell_of(&self, p: Point<N>) -> [i64; N] {
+        let c = (p - self.min) * (1.0 / self.cell);
+        c.vs.map(|v| v.floor() as i64)
+    }
+
+    fn remove(&mut self, p: Point<N>, index: usize) {
+        let key = self.cell_of(p);
+        if let Some(entries) = self.cells.get_mut(&key) {
+            let before = entries.len();
+            entries.retain(|(i, _)| *i != index);
+            self.len -= before - entries.len();
+            if entries.is_empty() {
+                self.cells.remove(&key);
+            }
+        }
+    }
+
+    // Visits the entries in the cells `r` steps from `centre`.
+    fn visit_shell<F>(&self, centre: [i64; N], r: i64, mut f: F)
+    where
+        F: FnMut(usize, bool),
+    {
+        let mut offset = [-r; N];
+        loop {
+            if offset.iter().any(|o| o.abs() == r) {
+                let mut key = centre;
+                for (k, o) in key.iter_mut().zip(offset.iter()) {
+                    *k += o;
+                }
+                for (index, is_end) in self.cells.get(&key).into_iter().flatten() {
+                    f(*index, *is_end);
+                }
+            }
+            // Step to the next offset, like an odometer.
+            let mut k = 0;
+            while k < N && offset[k] == r {
+                offset[k] = -r;
+                k += 1;
+            }
+            if k == N {
+                break;
+            }
+            offset[k] += 1;
+        }
+    }
+
+    // Up to `count` entries nearest `p`, closest first, judged by
+    // `distance2`, which gives None for entries to skip.
+    fn nearest<F>(&self, p: Point<N>, count: usize, distance2: F) -> Vec<(f32, usize, bool)>
+    where
+        F: Fn(usize, bool) -> Option<f32>,
+    {
+        let mut best: Vec<(f32, usize, bool)> = vec![];
+        if self.len == 0 || count == 0 {
+            return best;
+        }
+        let centre = self.cell_of(p);
+        // Past this every cell of the grid has been looked at.
+        let last = centre
+            .iter()
+            .map(|c| c.abs().max((c - self.size).abs()))
+            .max()
+            .unwrap_or(0);
+        for r in 0..=last {
+            self.visit_shell(centre, r, |index, is_end| {
+                if let Some(d) = distance2(index, is_end) {
+                    let at = best.partition_point(|b| b.0 <= d);
+                    if at < count {
+                        best.insert(at, (d, index, is_end));
+                        best.truncate(count);
+                    }
+                }
+            });
+            // Anything in the cells further out is at least this far away.
+            let beyond = r as f32 * self.cell;
+            if best.len() == count && best[count - 1].0 <= beyond * beyond {
+                break;
+            }
+        }
+        best
+    }
+}
+
+fn path_ends<const N: usize>(
+    paths: &[OrientedPath<N>],
+    with_ends: bool,
+) -> Vec<(usize, bool, Point<N>)> {
+    let mut ends: Vec<_> = paths
+        .iter()
+        .enumerate()
+        .map(|(i, p)| (i, false, p.start))
+        .collect();
+    if with_ends {
+        ends.extend(paths.iter().enumerate().map(|(i, p)| (i, true, p.end)));
+    }
+    ends
+}
+
+// Greedy nearest neighbour: always draw the closest remaining path next.
+fn nearest_neighbour<const N: usize>(
+    start: Point<N>,
+    paths: &[OrientedPath<N>],
+    allow_reverse: bool,
+) -> Vec<OrientedPath<N>> {
+    let mut grid = EndGrid::new(&path_ends(paths, allow_reverse));
+    let mut built = grid.len;
+    let mut done = vec![false; paths.len()];
+    let mut result = Vec::with_capacity(paths.len());
+    let mut current = start;
+    while result.len() < paths.len() {
+        // Searches get slow as the grid empties, so start again with
+        // bigger cells.
+        if grid.len * 4 < built {
+            let remaining: Vec<_> = path_ends(paths, allow_reverse)
+                .into_iter()
+                .filter(|(i, _, _)| !done[*i])
+                .collect();
+            grid = EndGrid::new(&remaining);
+            built = grid.len;
+        }
+        let (_, index, reversed) = grid.nearest(current, 1, |i, is_end| {
+            let p = if is_end { paths[i].end } else { paths[i].start };
+            Some((p - current).norm_squared())
+        })[0];
+        grid.remove(paths[index].start, index);
+        grid.remove(paths[index].end, index);
+        done[index] = true;
+        let p = if reversed {
+            paths[index].flip()
+        } else {
+            paths[index]
+        };
+        current = p.end;
+        result.push(p);
+    }
+    result
+}
+
+// 2-opt: reversing a run of paths (and each path within it) only changes
+// the two travel moves at either end of the run, so we look for runs
+// where that shortens the total. Only runs finishing at a path near the
+// one before them are tried.
+fn two_opt<const N: usize>(
+    start: Point<N>,
+    order: &mut [OrientedPath<N>],
+    passes: usize,
+    neighbours: usize,
+) {
+    let n = order.len();
+    if n < 2 || passes == 0 {
+        return;
+    }
+    let grid = EndGrid::new(&path_ends(order, true));
+    // The paths with an end nearest a point, other than the one at `skip`
+    // in the order as it is now. Paths are known by their `index`, as
+    // they move about in `order`.
+    let near = |p: Point<N>, skip: usize| -> Vec<usize> {
+        let mut result: Vec<usize> = grid
+            .nearest(p, neighbours, |i, is_end| {
+                let q = if is_end { order[i].end } else { order[i].start };
+                (i != skip).then(|| (q - p).norm_squared())
+            })
+            .into_iter()
+            .map(|(_, i, _)| order[i].index)
+            .collect();
+        result.sort_unstable();
+        result.dedup();
+        result
+    };
+    let start_near = near(start, n);
+    let indices = order.iter().map(|p| p.index).max().unwrap_or(0) + 1;
+    let mut by_path = vec![vec![]; indices];
+    let mut position = vec![0; indices];
+    for (i, p) in order.iter().enumerate() {
+        let mut list = near(p.start, i);
+        list.extend(near(p.end, i));
+        list.sort_unstable();
+        list.dedup();
+        by_path[p.index] = list;
+        position[p.index] = i;
+    }
+
+    for _ in 0..passes {
+        let mut improved = false;
+        for i in 0..n {
+            let (before, candidates) = if i == 0 {
+                (start, &start_near)
+            } else {
+                (order[i - 1].end, &by_path[order[i - 1].index])
+            };
+            for q in candidates {
+                let j = position[*q];
+                if j <= i {
+                    continue;
+                }
+                let mut delta = distance(before, order[j].end) - distance(before, order[i].start);
+                if j + 1 < n {
+                    let after = order[j + 1].start;
+                    delta += distance(order[i].start, after) - distance(order[j].end, after);
+                }
+                if delta < -1e-4 {
+                    order[i..=j].reverse();
+                    for (k, p) in order[i..=j].iter_mut().enumerate() {
+                        *p = p.flip();
+                        position[p.index] = i + k;
+                    }
+                    improved = true;
+                }
+            }
+        }
+        if !improved {
+            break;
+        }
+    }
+}
+
+// Reorders (and optionally reverses) the lines to reduce the pen-up travel
+// between them, starting from `start`. Empty lines are kept, at the end.
+pub fn order_paths<const N: usize, A>(
+    start: Point<N>,
+    lines: &[PolyLine<N, A>],
+    options: PathOrderOptions,
+) -> (Vec<PolyLine<N, A>>, PathOrderReport)
+where
+    A: AttributeReverse + Clone,
+{
+    let travel_before = travel_distance(start, lines);
+    let paths: Vec<OrientedPath<N>> = lines
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| !line.ps.is_empty())
+        .map(|(index, line)| OrientedPath {
+            index,
+            reversed: false,
+            start: line.ps[0],
+            end: if line.closed {
+                line.ps[0]
+            } else {
+                *line.ps.last().unwrap()
+            },
+        })
+        .collect();
+
+    let mut order = nearest_neighbour(start, &paths, options.allow_reverse);
+    if options.allow_reverse {
+        two_opt(
+            start,
+            &mut order,
+            options.two_opt_passes,
+            options.neighbours,
+        );
+    }
+
+    let result = order
+        .iter()
+        .map(|p| {
+            if p.reversed {
+                lines[p.index].reverse()
+            } else {
+                lines[p.index].clone()
+            }
+        })
+        .chain(lines.iter().filter(|line| line.ps.is_empty()).cloned())
+        .collect();
+    let travel_after = oriented_travel(start, &order);
+    (
+        result,
+        PathOrderReport {
+            travel_before,
+            travel_after,
+        },
+    )
+}
+
+impl<const N: usize> LineSet<N> {
+    pub fn ordered(
+        &self,
+        start: Point<N>,
+        options: PathOrderOptions,
+    ) -> (LineSet<N>, PathOrderReport) {
+        let (lines, report) = order_paths(start, &self.lines, options);
+        (LineSet { lines }, report)
+    }
+}
+
+// Pipeline stage that collects all incoming polylines, then sends them
+// on in an order that reduces pen-up travel.
+pub struct PathOrderer<const N: usize> {
+    pub input: Receiver<PolyLine<N, ()>>,
+    pub output: Sender<PolyLine<N, ()>>,
+    pub start: Point<N>,
+    pub options: PathOrderOptions,
+}
+
+impl<const N: usize> PathOrderer<N> {
+    pub fn new(
+        input: Receiver<PolyLine<N, ()>>,
+        output: Sender<PolyLine<N, ()>>,
+        start: Point<N>,
+        options: PathOrderOptions,
+    ) -> PathOrderer<N> {
+        PathOrderer {
+            input,
+            output,
+            start,
+            options,
+        }
+    }
+
+    pub async fn run(mut self) -> Result<PathOrderReport, ()> {
+        let mut lines = vec![];
+        while let Some(pl) = self.input.recv().await {
+            lines.push(pl);
+        }
+        let (lines, report) = order_paths(self.start, &lines, self.options);
+        for pl in lines {
+            self.output.send(pl).await.map_err(|_| ())?;
+        }
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::n2::point::p2;
+
+    fn line(a: Point<2>, b: Point<2>) -> PolyLine<2, ()> {
+        PolyLine {
+            ps: vec![a, b],
+            attributes: (),
+            closed: false,
+        }
+    }
+
+    #[test]
+    pub fn reorders_and_reverses() {
+        // Three horizontal strokes given in a bad order, all left-to-right.
+        let lines = LineSet {
+            lines: vec![
+                line(p2(0.0, 20.0), p2(10.0, 20.0)),
+                line(p2(0.0, 0.0), p2(10.0, 0.0)),
+                line(p2(0.0, 10.0), p2(10.0, 10.0)),
+            ],
+        };
+        let (ordered, report) = lines.ordered(p2(0.0, 0.0), PathOrderOptions::default());
+        assert_eq!(ordered.lines.len(), 3);
+        // Boustrophedon: each stroke starts where the previous one ended.
+        assert_eq!(ordered.lines[0].ps, vec![p2(0.0, 0.0), p2(10.0, 0.0)]);
+        assert_eq!(ordered.lines[1].ps, vec![p2(10.0, 10.0), p2(0.0, 10.0)]);
+        assert_eq!(ordered.lines[2].ps, vec![p2(0.0, 20.0), p2(10.0, 20.0)]);
+        assert!((report.travel_after - 20.0).abs() < 1e-4);
+        assert!(report.travel_before > report.travel_after);
+
+        // Empty lines aren't drawn, but are kept at the end.
+        let mut with_empty = lines.clone();
+        with_empty.lines.insert(
+            1,
+            PolyLine {
+                ps: vec![],
+                ..line(p2(0.0, 0.0), p2(0.0, 0.0))
+            },
+        );
+        let (ordered, _) = with_empty.ordered(p2(0.0, 0.0), PathOrderOptions::default());
+        assert_eq!(ordered.lines.len(), 4);
+        assert!(ordered.lines[3].ps.is_empty());
+    }
+
+    #[test]
+    pub fn nearest_neighbour_matches_brute_force() {
+        // Scattered strokes, some far from the rest.
+        let paths: Vec<OrientedPath<2>> = (0..300)
+            .map(|i| {
+                let x = ((i * 37) % 101) as f32 * if i % 50 == 0 { 10.0 } else { 1.0 };
+                let y = ((i * 53) % 97) as f32;
+                OrientedPath {
+                    index: i,
+                    reversed: false,
+                    start: p2(x, y),
+                    end: p2(x + ((i * 7) % 5) as f32, y + 2.0),
+                }
+            })
+            .collect();
+        let start = p2(50.0, 50.0);
+        for allow_reverse in [false, true] {
+            let order = nearest_neighbour(start, &paths, allow_reverse);
+            let mut remaining = paths.clone();
+            let mut current = start;
+            for p in &order {
+                let best = remaining
+                    .iter()
+                    .flat_map(|q| [Some(q.start), allow_reverse.then_some(q.end)])
+                    .flatten()
+                    .map(|q| distance(current, q))
+                    .fold(f32::INFINITY, f32::min);
+                assert_eq!(distance(current, p.start), best);
+                remaining.retain(|q| q.index != p.index);
+                current = p.end;
+            }
+            assert!(remaining.is_empty());
+        }
+    }
+
+    #[test]
+    pub fn two_opt_never_makes_things_worse() {
+        let lines: Vec<_> = (0..50)
+            .map(|i| {
+                let x = ((i * 37) % 50) as f32;
+                let y = ((i * 11) % 7) as f32 * 10.0;
+                line(p2(x, y), p2(x + 1.0, y + 3.0))
+            })
+            .collect();
+        let start = p2(0.0, 0.0);
+        let (greedy, _) = order_paths(
+            start,
+            &lines,
+            PathOrderOptions {
+                two_opt_passes: 0,
+                ..Default::default()
+            },
+        );
+        let (improved, report) = order_paths(start, &lines, PathOrderOptions::default());
+        assert_eq!(improved.len(), lines.len());
+        assert!(travel_distance(start, &improved) <= travel_distance(start, &greedy) + 1e-3);
+        assert!((travel_distance(start, &improved) - report.travel_after).abs() < 1e-2);
+    }
+}
@@ -0,0 +1,274 @@
+use tokio::sync::mpsc::Sender;
+
+use crate::nbase::lineset::LineSet;
+use crate::nbase::polyline::PolyLine;
+
+const CHECKPOINT_HEADER: &str = "gnarled-plot-checkpoint 1";
+
+#[derive(Debug)]
+pub enum CheckpointError {
+    Io(std::io::Error),
+    InvalidFormat(String),
+    // The checkpoint was saved for a different set of lines.
+    JobMismatch,
+}
+
+impl From<std::io::Error> for CheckpointError {
+    fn from(e: std::io::Error) -> Self {
+        CheckpointError::Io(e)
+    }
+}
+
+// How far through a job the plotter got: all paths before `path_index`
+// are done, and `distance` has been drawn along `path_index`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PlotProgress {
+    pub path_index: usize,
+    pub distance: f32,
+}
+
+// A plot that can be stopped and resumed. The lines should already be in
+// their final order (see `LineSet::ordered`), as progress refers to it.
+#[derive(Clone)]
+pub struct PlotJob<const N: usize> {
+    pub lines: LineSet<N>,
+    pub progress: PlotProgress,
+}
+
+impl<const N: usize> PlotJob<N> {
+    pub fn new(lines: LineSet<N>) -> PlotJob<N> {
+        PlotJob {
+            lines,
+            progress: PlotProgress::default(),
+        }
+    }
+
+    pub fn total_length(&self) -> f32 {
+        self.lines.lines.iter().map(|l| l.length()).sum()
+    }
+
+    // Pen-down distance drawn so far.
+    pub fn completed_length(&self) -> f32 {
+        let done: f32 = self
+            .lines
+            .lines
+            .iter()
+            .take(self.progress.path_index)
+            .map(|l| l.length())
+            .sum();
+        done + self.progress.distance
+    }
+
+    pub fn fraction_complete(&self) -> f32 {
+        let total = self.total_length();
+        if total > 0.0 {
+            (self.completed_length() / total).min(1.0)
+        } else if self.is_finished() {
+            1.0
+        } else {
+            0.0
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.progress.path_index >= self.lines.lines.len()
+    }
+
+    // Records that the pen has drawn a further `d`, moving on to
+    // later paths as each is completed.
+    pub fn advance(&mut self, d: f32) {
+        self.progress.distance += d;
+        while let Some(line) = self.lines.lines.get(self.progress.path_index) {
+            let l = line.length();
+            if self.progress.distance < l {
+                break;
+            }
+            self.progress.distance -= l;
+            self.progress.path_index += 1;
+        }
+        if self.is_finished() {
+            self.progress.distance = 0.0;
+        }
+    }
+
+    // Records that the current path has been drawn to its end.
+    pub fn complete_path(&mut self) {
+        if !self.is_finished() {
+            self.progress.path_index += 1;
+            self.progress.distance = 0.0;
+        }
+    }
+
+    // Everything still to be drawn, with the first line starting
+    // exactly where the plot stopped.
+    pub fn remaining(&self) -> LineSet<N> {
+        let mut lines = vec![];
+        if let Some(current) = self.lines.lines.get(self.progress.path_index) {
+            lines.extend(current.sub_path(self.progress.distance, current.length()));
+            lines.extend_from_slice(&self.lines.lines[self.progress.path_index + 1..]);
+        }
+        LineSet { lines }
+    }
+
+    pub async fn send_remaining(&self, output: &Sender<PolyLine<N, ()>>) -> Result<(), ()> {
+        for pl in self.remaining().lines {
+            output.send(pl).await.map_err(|_| ())?;
+        }
+        Ok(())
+    }
+
+    // Writes the progress, along with enough about the job to detect
+    // a checkpoint being loaded against different lines.
+    pub fn write_checkpoint<W>(&self, w: &mut W) -> Result<(), std::io::Error>
+    where
+        W: std::io::Write,
+    {
+        writeln!(w, "{}", CHECKPOINT_HEADER)?;
+        writeln!(w, "paths {}", self.lines.lines.len())?;
+        writeln!(w, "length {}", self.total_length())?;
+        writeln!(w, "path_index {}", self.progress.path_index)?;
+        writeln!(w, "distance {}", self.progress.distance)
+    }
+
+    pub fn save_checkpoint<P>(&self, path: P) -> Result<(), std::io::Error>
+    where
+        P: AsRef<std::path::Path>,
+    {
+        // Write then rename, so a crash mid-write can't lose the old checkpoint.
+        let path = path.as_ref();
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        let mut f = std::fs::File::create(&tmp)?;
+        self.write_checkpoint(&mut f)?;
+        f.sync_all()?;
+        std::fs::rename(&tmp, path)
+    }
+
+    // Restores progress from a checkpoint written for the same lines.
+    pub fn read_checkpoint<R>(&mut self, r: &mut R) -> Result<(), CheckpointError>
+    where
+        R: std::io::Read,
+    {
+        let mut s = String::new();
+        r.read_to_string(&mut s)?;
+        let mut lines = s.lines();
+        if lines.next() != Some(CHECKPOINT_HEADER) {
+            return Err(CheckpointError::InvalidFormat(
+                "missing checkpoint header".to_string(),
+            ));
+        }
+        let mut field = |name: &str| -> Result<&str, CheckpointError> {
+            lines
+                .next()
+                .and_then(|l| l.strip_prefix(name))
+                .and_then(|l| l.strip_prefix(' '))
+                .ok_or_else(|| CheckpointError::InvalidFormat(format!("expected {}", name)))
+        };
+        let invalid = |s: &str| CheckpointError::InvalidFormat(s.to_string());
+        let paths: usize = field("paths")?.parse().map_err(|_| invalid("paths"))?;
+        let length: f32 = field("length")?.parse().map_err(|_| invalid("length"))?;
+        let path_index: usize = field("path_index")?
+            .parse()
+            .map_err(|_| invalid("path_index"))?;
+        let distance: f32 = field("distance")?
+            .parse()
+            .map_err(|_| invalid("distance"))?;
+
+        let total = self.total_length();
+        if paths != self.lines.lines.len() || (length - total).abs() > 1e-3 * total.max(1.0) {
+            return Err(CheckpointError::JobMismatch);
+        }
+        if path_index > paths {
+            return Err(invalid("path_index"));
+        }
+        // Somewhere along the current path, or nowhere once they are done.
+        let path_length = self.lines.lines.get(path_index).map_or(0.0, |l| l.length());
+        if !(distance >= 0.0 && distance <= path_length + 1e-3 * path_length.max(1.0)) {
+            return Err(invalid("distance"));
+        }
+        self.progress = PlotProgress {
+            path_index,
+            distance,
+        };
+        Ok(())
+    }
+
+    pub fn load_checkpoint<P>(&mut self, path: P) -> Result<(), CheckpointError>
+    where
+        P: AsRef<std::path::Path>,
+    {
+        let mut f = std::fs::File::open(path)?;
+        self.read_checkpoint(&mut f)
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::n2::point::p2;
+
+    fn job() -> PlotJob<2> {
+        PlotJob::new(LineSet {
+            lines: vec![
+                PolyLine {
+                    ps: vec![p2(0.0, 0.0), p2(10.0, 0.0), p2(10.0, 10.0)],
+                    attributes: (),
+                    closed: false,
+                },
+                PolyLine {
+                    ps: vec![p2(0.0, 20.0), p2(10.0, 20.0)],
+                    attributes: (),
+                    closed: false,
+                },
+            ],
+        })
+    }
+
+    #[test]
+    pub fn resume_from_checkpoint() {
+        let mut a = job();
+        a.advance(15.0);
+        assert_eq!(
+            a.progress,
+            PlotProgress {
+                path_index: 0,
+                distance: 15.0
+            }
+        );
+        assert!((a.fraction_complete() - 0.5).abs() < 1e-6);
+
+        let mut checkpoint = vec![];
+        a.write_checkpoint(&mut checkpoint).unwrap();
+        let mut b = job();
+        b.read_checkpoint(&mut checkpoint.as_slice()).unwrap();
+        assert_eq!(b.progress, a.progress);
+
+        let remaining = b.remaining();
+        assert_eq!(remaining.lines.len(), 2);
+        assert_eq!(remaining.lines[0].ps, vec![p2(10.0, 5.0), p2(10.0, 10.0)]);
+        assert_eq!(remaining.lines[1].ps, vec![p2(0.0, 20.0), p2(10.0, 20.0)]);
+
+        b.advance(5.0);
+        assert_eq!(b.progress.path_index, 1);
+        b.advance(100.0);
+        assert!(b.is_finished());
+        assert!(b.remaining().lines.is_empty());
+
+        // Distances that aren't along the current path are rejected.
+        for distance in ["NaN", "-1", "25", "inf"] {
+            let bad = String::from_utf8(checkpoint.clone())
+                .unwrap()
+                .replace("distance 15", &format!("distance {}", distance));
+            assert!(matches!(
+                job().read_checkpoint(&mut bad.as_bytes()),
+                Err(CheckpointError::InvalidFormat(_))
+            ));
+        }
+
+        let mut other: PlotJob<2> = PlotJob::new(LineSet { lines: vec![] });
+        assert!(matches!(
+            other.read_checkpoint(&mut checkpoint.as_slice()),
+            Err(CheckpointError::JobMismatch)
+        ));
+    }
+}
@@ -0,0 +1,174 @@
+use crate::nbase::bounds::Bounds;
+use crate::nbase::lineset::LineSet;
+use crate::nbase::point::{distance, Point};
+use crate::nbase::traits::Boundable;
+
+// A simple model of a plotter's motion, in the same units as the
+// artwork (e.g. mm and seconds).
+#[derive(Clone, Copy, Debug)]
+pub struct PlotterModel<const N: usize = 2> {
+    // Top speed while drawing, units/s.
+    pub max_draw_speed: f32,
+    // Top speed while travelling with the pen up, units/s.
+    pub max_travel_speed: f32,
+    // Acceleration (and deceleration), units/s^2.
+    pub acceleration: f32,
+    // Seconds taken to raise or lower the pen.
+    pub pen_lift_time: f32,
+    // Turns sharper than this (in radians) bring the pen to a stop,
+    // gentler ones are taken at speed.
+    pub cornering_angle: f32,
+    // Where the pen starts from and returns to at the end, in artwork units.
+    pub home: Point<N>,
+}
+
+impl<const N: usize> Default for PlotterModel<N> {
+    // Roughly an AxiDraw V3 at its default settings, in mm, homed at the
+    // origin.
+    fn default() -> Self {
+        PlotterModel {
+            max_draw_speed: 65.0,
+            max_travel_speed: 190.0,
+            acceleration: 1000.0,
+            pen_lift_time: 0.2,
+            cornering_angle: 0.5,
+            home: Point::zero(),
+        }
+    }
+}
+
+impl<const N: usize> PlotterModel<N> {
+    // Time to move `d` from rest to rest with a trapezoidal (or, for short
+    // moves, triangular) velocity profile.
+    pub fn move_time(&self, d: f32, max_speed: f32) -> f32 {
+        if d <= 0.0 {
+            return 0.0;
+        }
+        let a = self.acceleration;
+        // Distance needed to reach full speed and stop again.
+        let ramp = max_speed * max_speed / a;
+        if d >= ramp {
+            2.0 * max_speed / a + (d - ramp) / max_speed
+        } else {
+            2.0 * (d / a).sqrt()
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct PlotStats<const N: usize> {
+    pub pen_down_length: f32,
+    pub pen_up_length: f32,
+    pub pen_lifts: usize,
+    pub segment_count: usize,
+    pub bounds: Option<Bounds<N>>,
+    // Estimated plot time in seconds.
+    pub estimated_duration: f32,
+}
+
+impl<const N: usize> LineSet<N> {
+    // Statistics for plotting the lines in their current order, starting
+    // from and returning to the model's home point.
+    pub fn plot_stats(&self, model: &PlotterModel<N>) -> PlotStats<N> {
+        let mut stats = PlotStats {
+            pen_down_length: 0.0,
+            pen_up_length: 0.0,
+            pen_lifts: 0,
+            segment_count: 0,
+            bounds: self.bounds(),
+            estimated_duration: 0.0,
+        };
+        let travel = |stats: &mut PlotStats<N>, from: Point<N>, to: Point<N>| {
+            let d = distance(from, to);
+            stats.pen_up_length += d;
+            stats.estimated_duration += model.move_time(d, model.max_travel_speed);
+        };
+        let mut current: Option<Point<N>> = None;
+        for line in &self.lines {
+            let ps = line.path_points();
+            if ps.len() < 2 {
+                continue;
+            }
+            travel(&mut stats, current.unwrap_or(model.home), ps[0]);
+            stats.pen_lifts += 1;
+            // Lowering then raising the pen.
+            stats.estimated_duration += 2.0 * model.pen_lift_time;
+            // Length drawn since the pen last came to a stop.
+            let mut run = 0.0;
+            let cos_corner = model.cornering_angle.cos();
+            for (i, w) in ps.windows(2).enumerate() {
+                let d = distance(w[0], w[1]);
+                stats.pen_down_length += d;
+                stats.segment_count += 1;
+                run += d;
+                if let Some(next) = ps.get(i + 2) {
+                    let u = w[1] - w[0];
+                    let v = *next - w[1];
+                    let uv = u.norm_squared().sqrt() * v.norm_squared().sqrt();
+                    if uv > 0.0 && u.dot(v) >= cos_corner * uv {
+                        continue;
+                    }
+                }
+                stats.estimated_duration += model.move_time(run, model.max_draw_speed);
+                run = 0.0;
+            }
+            current = ps.last().copied();
+        }
+        if let Some(current) = current {
+            travel(&mut stats, current, model.home);
+        }
+        stats
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::n2::point::p2;
+    use crate::nbase::polyline::PolyLine;
+
+    #[test]
+    pub fn stats_and_duration() {
+        let model = PlotterModel {
+            max_draw_speed: 10.0,
+            max_travel_speed: 20.0,
+            acceleration: 100.0,
+            pen_lift_time: 0.5,
+            cornering_angle: 0.5,
+            home: p2(0.0, 0.0),
+        };
+        let line = |ps: Vec<Point<2>>| PolyLine {
+            ps,
+            attributes: (),
+            closed: false,
+        };
+        let lines = LineSet {
+            lines: vec![
+                // Stops at the corner.
+                line(vec![p2(10.0, 0.0), p2(20.0, 0.0), p2(20.0, 10.0)]),
+                line(vec![]),
+                // Doesn't stop in the middle.
+                line(vec![p2(20.0, 20.0), p2(25.0, 20.0), p2(30.0, 20.0)]),
+            ],
+        };
+        let stats = lines.plot_stats(&model);
+        assert_eq!(stats.pen_down_length, 30.0);
+        // Out from home, between the lines and back home.
+        let home_again = 1300.0f32.sqrt();
+        assert!((stats.pen_up_length - (10.0 + 10.0 + home_again)).abs() < 1e-4);
+        assert_eq!(stats.pen_lifts, 2);
+        assert_eq!(stats.segment_count, 4);
+        let bounds = stats.bounds.unwrap();
+        assert_eq!((bounds.min, bounds.max), (p2(10.0, 0.0), p2(30.0, 20.0)));
+
+        // Travel takes 4 units to speed up and slow down, so 10 units take
+        // 0.4 + 6 / 20 s. Drawing takes 1 unit, so 10 units take
+        // 0.2 + 9 / 10 s.
+        let travel = 0.7 + 0.7 + 0.4 + (home_again - 4.0) / 20.0;
+        let draw = 3.0 * 1.1;
+        let pen = 2.0 * 2.0 * 0.5;
+        assert!((stats.estimated_duration - (travel + draw + pen)).abs() < 1e-4);
+        // Too short to reach full speed.
+        assert!((model.move_time(1.0, 20.0) - 0.2).abs() < 1e-6);
+    }
+}
@@ -24,6 +24,7 @@ pub trait Float:
     fn sqrt(self) -> Self;
     fn abs(self) -> Self;
     fn nan() -> Self;
+    fn epsilon() -> Self;
     fn min(a: Self, b: Self) -> Self;
     fn max(a: Self, b: Self) -> Self;
     fn ln(self) -> Self;
@@ -58,6 +59,10 @@ impl Float for f32 {
         f32::NAN
     }
 
+    fn epsilon() -> Self {
+        f32::EPSILON
+    }
+
     fn min(a: Self, b: Self) -> Self {
         f32::min(a, b)
     }
@@ -112,6 +117,10 @@ impl Float for f64 {
         f64::NAN
     }
 
+    fn epsilon() -> Self {
+        f64::EPSILON
+    }
+
     fn min(a: Self, b: Self) -> Self {
         f64::min(a, b)
     }
@@ -9,11 +9,39 @@ use crate::nbase::traits::*;
 pub struct PolyLine<const N: usize, A> {
     pub ps: Vec<Point<N>>,
     pub attributes: A,
+    // A closed line has an implicit final segment back to its first point,
+    // which isn't repeated in `ps`.
+    // `new` and `closed` build lines without spelling this out.
+    pub closed: bool,
+}
+
+impl<const N: usize, A> PolyLine<N, A> {
+    // An open line through the points.
+    pub fn new(ps: Vec<Point<N>>, attributes: A) -> PolyLine<N, A> {
+        PolyLine {
+            ps,
+            attributes,
+            closed: false,
+        }
+    }
+
+    // A line through the points and back to the first.
+    pub fn closed(ps: Vec<Point<N>>, attributes: A) -> PolyLine<N, A> {
+        PolyLine {
+            ps,
+            attributes,
+            closed: true,
+        }
+    }
 }
 
 pub trait PolyLineAttribute {
     type LineAttribute;
     fn attribute_for_line_segment(&self, index: usize) -> Self::LineAttribute;
+    // The attributes for the part of the line from `start` to `end`, each
+    // given as (segment index, parameter along the segment). On a closed
+    // line `end` may come before `start`, meaning the part runs from
+    // `start` round through the first point to `end`.
     fn poly_range(&self, start: (usize, f32), end: (usize, f32)) -> Self;
 }
 
@@ -51,24 +79,50 @@ impl<const N: usize, A> PolyLine<N, A> {
         if self.ps.is_empty() {
             return LineSet { lines: vec![] };
         }
+        if !self.closed {
+            return self.clip_open_by(&self.ps, 0, f);
+        }
+        // Walk a closed line starting from a point that gets clipped away,
+        // so the piece passing through ps[0] isn't split in two there.
+        match self.ps.iter().position(|p| f(*p) < 0.0) {
+            None => LineSet {
+                lines: vec![self.map_attribute(|_| ())],
+            },
+            Some(k) => {
+                let ps: Vec<Point<N>> = self.ps[k..]
+                    .iter()
+                    .chain(self.ps[..=k].iter())
+                    .cloned()
+                    .collect();
+                self.clip_open_by(&ps, k, f)
+            }
+        }
+    }
 
+    // Clips the open line through `ps`, whose first point is
+    // self.ps[offset], keeping the parts where f is non-negative.
+    fn clip_open_by<F>(&self, ps: &[Point<N>], offset: usize, f: F) -> LineSet<N>
+    where
+        A: PolyLineAttribute,
+        F: Fn(Point<N>) -> f32,
+    {
+        let index = |i: usize| (i + offset) % self.ps.len();
         let mut lines: Vec<PolyLine<N, A>> = vec![];
-        let a = f(self.ps[0]);
+        let a = f(ps[0]);
         let mut state = SegmentState {
-            last_p: self.ps[0],
+            last_p: ps[0],
             last_a: a,
             open_segment: if a >= 0.0 {
                 Some(OpenSegment {
-                    line: vec![self.ps[0]],
-                    start_iz: (0, 0.0),
+                    line: vec![ps[0]],
+                    start_iz: (index(0), 0.0),
                 })
             } else {
                 None
             },
         };
 
-        for i in 1..self.ps.len() {
-            let p = &self.ps[i];
+        for (i, p) in ps.iter().enumerate().skip(1) {
             let a = f(*p);
             let new_segment = match state.open_segment.take() {
                 Some(mut seg) => {
@@ -80,12 +134,17 @@ impl<const N: usize, A> PolyLine<N, A> {
                         // Segment done.
                         let da = a - state.last_a;
                         let alpha = a / da;
-                        let pp = Point::lerp(1.0-alpha, state.last_p, *p);
+                        // The crossing is 1 - alpha along the segment from
+                        // last_p, which is what poly_range is given too.
+                        let pp = Point::lerp(1.0 - alpha, state.last_p, *p);
                         seg.line.push(pp);
 
                         lines.push(PolyLine {
                             ps: seg.line,
-                            attributes: self.attributes.poly_range(seg.start_iz, (i - 1, alpha)),
+                            attributes: self
+                                .attributes
+                                .poly_range(seg.start_iz, (index(i - 1), 1.0 - alpha)),
+                            closed: false,
                         });
                         None
                     }
@@ -95,10 +154,10 @@ impl<const N: usize, A> PolyLine<N, A> {
                         // Start a new segment
                         let da = a - state.last_a;
                         let alpha = a / da;
-                        let pp = Point::lerp(1.0-alpha, state.last_p, *p);
+                        let pp = Point::lerp(1.0 - alpha, state.last_p, *p);
                         let seg = OpenSegment {
-                            line: vec![pp],
-                            start_iz: (i - 1, alpha),
+                            line: vec![pp, *p],
+                            start_iz: (index(i - 1), 1.0 - alpha),
                         };
                         Some(seg)
                     } else {
@@ -116,7 +175,8 @@ impl<const N: usize, A> PolyLine<N, A> {
                 ps: seg.line,
                 attributes: self
                     .attributes
-                    .poly_range(seg.start_iz, (self.ps.len() - 1, 1.0)),
+                    .poly_range(seg.start_iz, (index(ps.len() - 1), 1.0)),
+                closed: false,
             });
         }
 
@@ -135,15 +195,26 @@ impl<const N: usize, A> PolyLine<N, A> {
         if self.ps.len() < 2 {
             return result;
         }
-        for i in 0..self.ps.len() - 1 {
+        let ps = self.path_points();
+        for i in 0..ps.len() - 1 {
             result.push(LineSegment {
-                ps: [self.ps[i], self.ps[i + 1]],
+                ps: [ps[i], ps[i + 1]],
                 attributes: self.attributes.attribute_for_line_segment(i),
             });
         }
         result
     }
 
+    // The points in the order they are drawn, so a closed line
+    // finishes back at its first point.
+    pub fn path_points(&self) -> Vec<Point<N>> {
+        let mut ps = self.ps.clone();
+        if self.closed && ps.len() > 1 {
+            ps.push(ps[0]);
+        }
+        ps
+    }
+
     pub(crate) fn reverse(&self) -> PolyLine<N, A>
     where
         A: AttributeReverse,
@@ -153,6 +224,7 @@ impl<const N: usize, A> PolyLine<N, A> {
         PolyLine {
             ps,
             attributes: self.attributes.reverse(),
+            closed: self.closed,
         }
     }
 }
@@ -165,6 +237,7 @@ impl<const N: usize, A> PolyLine<N, A> {
         PolyLine {
             ps: self.ps.clone(),
             attributes: f(&self.attributes),
+            closed: self.closed,
         }
     }
 }
@@ -186,6 +259,7 @@ where
         PolyLine {
             ps: self.ps.iter().map(|p| *p + d).collect(),
             attributes: self.attributes.clone(),
+            closed: self.closed,
         }
     }
 }
@@ -204,6 +278,110 @@ where
                 .map(|p| (*p - c) * Point::from(*s) + c)
                 .collect(),
             attributes: self.attributes.clone(),
+            closed: self.closed,
         }
     }
 }
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::n2::point::p2;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    fn square() -> PolyLine<2, ()> {
+        PolyLine {
+            ps: vec![p2(0.0, 0.0), p2(10.0, 0.0), p2(10.0, 10.0), p2(0.0, 10.0)],
+            attributes: (),
+            closed: true,
+        }
+    }
+
+    #[test]
+    pub fn constructors() {
+        let ps = vec![p2(0.0, 0.0), p2(1.0, 0.0), p2(1.0, 1.0)];
+        let open = PolyLine::new(ps.clone(), ());
+        assert!(!open.closed);
+        assert_eq!(open.line_segments().len(), 2);
+        let closed = PolyLine::closed(ps, ());
+        assert!(closed.closed);
+        assert_eq!(closed.path_points().last(), Some(&p2(0.0, 0.0)));
+    }
+
+    #[test]
+    pub fn clip_closed_without_seam() {
+        // Keep x <= 5: the left half of the square, which passes through
+        // the first point, should come out as one piece.
+        let clipped = square().clip_by(p2(-1.0, 0.0), -5.0);
+        assert_eq!(clipped.lines.len(), 1);
+        let line = &clipped.lines[0];
+        assert!(!line.closed);
+        assert_eq!(
+            line.ps,
+            vec![p2(5.0, 10.0), p2(0.0, 10.0), p2(0.0, 0.0), p2(5.0, 0.0)]
+        );
+
+        // Nothing clipped leaves the loop closed.
+        let clipped = square().clip_by(p2(1.0, 0.0), -1.0);
+        assert_eq!(clipped.lines.len(), 1);
+        assert!(clipped.lines[0].closed);
+        assert_eq!(clipped.lines[0].line_segments().len(), 4);
+    }
+
+    #[test]
+    pub fn clip_keeps_vertices_after_entering() {
+        // Keep x >= 0: the line comes in, turns and goes back out.
+        let line = PolyLine {
+            ps: vec![p2(-2.0, 0.0), p2(2.0, 0.0), p2(2.0, 2.0), p2(-2.0, 2.0)],
+            attributes: (),
+            closed: false,
+        };
+        let clipped = line.clip_by(p2(1.0, 0.0), 0.0);
+        assert_eq!(clipped.lines.len(), 1);
+        assert_eq!(
+            clipped.lines[0].ps,
+            vec![p2(0.0, 0.0), p2(2.0, 0.0), p2(2.0, 2.0), p2(0.0, 2.0)]
+        );
+    }
+
+    // Records the ranges it is asked for, as clip_by drops the
+    // attributes of the pieces.
+    #[derive(Clone, Debug, Default)]
+    struct Ranges(Rc<RefCell<Vec<Range>>>);
+
+    type Range = ((usize, f32), (usize, f32));
+
+    impl PolyLineAttribute for Ranges {
+        type LineAttribute = ();
+        fn attribute_for_line_segment(&self, _index: usize) -> Self::LineAttribute {}
+        fn poly_range(&self, start: (usize, f32), end: (usize, f32)) -> Self {
+            self.0.borrow_mut().push((start, end));
+            self.clone()
+        }
+    }
+
+    #[test]
+    pub fn clip_poly_range_parameters() {
+        // Keep x >= 1: the line crosses a quarter of the way along its
+        // first segment and three quarters of the way along its third.
+        let ranges = Ranges::default();
+        let line = PolyLine {
+            ps: vec![p2(0.0, 0.0), p2(4.0, 0.0), p2(4.0, 1.0), p2(0.0, 1.0)],
+            attributes: ranges.clone(),
+            closed: false,
+        };
+        line.clip_by(p2(1.0, 0.0), 1.0);
+        assert_eq!(*ranges.0.borrow(), vec![((0, 0.25), (2, 0.75))]);
+
+        // Closing it only adds a segment that is clipped away.
+        let ranges = Ranges::default();
+        let line = PolyLine {
+            attributes: ranges.clone(),
+            closed: true,
+            ..line
+        };
+        line.clip_by(p2(1.0, 0.0), 1.0);
+        assert_eq!(*ranges.0.borrow(), vec![((0, 0.25), (2, 0.75))]);
+    }
+}
@@ -0,0 +1,333 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+use tokio::sync::mpsc::{Receiver, Sender};
+
+use crate::nbase::lineset::LineSet;
+use crate::nbase::point::{distance, Point};
+use crate::nbase::polyline::PolyLine;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SimplifyMethod {
+    // Keeps the point furthest from the chord until they are all close.
+    RamerDouglasPeucker,
+    // Drops the point making the smallest triangle with its neighbours
+    // until they are all big enough. Tends to give smoother curves.
+    VisvalingamWhyatt,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct SimplifyOptions {
+    pub method: SimplifyMethod,
+    // For Ramer-Douglas-Peucker, how far a dropped point may be from the
+    // simplified line. Visvalingam-Whyatt drops points whose triangle has
+    // an area under tolerance^2.
+    pub tolerance: f32,
+    // Points where the line turns by more than this (in radians) are
+    // always kept, so corners stay sharp.
+    pub corner_angle: f32,
+}
+
+impl Default for SimplifyOptions {
+    fn default() -> Self {
+        SimplifyOptions {
+            method: SimplifyMethod::RamerDouglasPeucker,
+            tolerance: 0.1,
+            corner_angle: std::f32::consts::FRAC_PI_4,
+        }
+    }
+}
+
+fn segment_distance<const N: usize>(p: Point<N>, a: Point<N>, b: Point<N>) -> f32 {
+    let d = b - a;
+    let l2 = d.norm_squared();
+    if l2 == 0.0 {
+        return distance(p, a);
+    }
+    let t = ((p - a).dot(d) / l2).clamp(0.0, 1.0);
+    distance(p, a + d * t)
+}
+
+fn triangle_area<const N: usize>(a: Point<N>, b: Point<N>, c: Point<N>) -> f32 {
+    let (u, v) = (b - a, c - a);
+    let uv = u.dot(v);
+    0.5 * (u.norm_squared() * v.norm_squared() - uv * uv)
+        .max(0.0)
+        .sqrt()
+}
+
+// Marks the points between `a` and `b` to keep.
+fn ramer_douglas_peucker<const N: usize>(
+    ps: &[Point<N>],
+    a: usize,
+    b: usize,
+    tolerance: f32,
+    keep: &mut [bool],
+) {
+    let mut spans = vec![(a, b)];
+    while let Some((a, b)) = spans.pop() {
+        let furthest = (a + 1..b)
+            .map(|i| (i, segment_distance(ps[i], ps[a], ps[b])))
+            .max_by(|x, y| x.1.total_cmp(&y.1));
+        if let Some((i, d)) = furthest {
+            if d > tolerance {
+                keep[i] = true;
+                spans.push((a, i));
+                spans.push((i, b));
+            }
+        }
+    }
+}
+
+// A point that could be dropped, smallest area first.
+struct Candidate {
+    area: f32,
+    index: usize,
+}
+
+impl PartialEq for Candidate {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Candidate {}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .area
+            .total_cmp(&self.area)
+            .then(other.index.cmp(&self.index))
+    }
+}
+
+// Marks the points between `a` and `b` to keep.
+fn visvalingam_whyatt<const N: usize>(
+    ps: &[Point<N>],
+    a: usize,
+    b: usize,
+    tolerance: f32,
+    keep: &mut [bool],
+) {
+    let min_area = tolerance * tolerance;
+    let mut prev: Vec<usize> = (0..ps.len()).map(|i| i.saturating_sub(1)).collect();
+    let mut next: Vec<usize> = (1..=ps.len()).collect();
+    let mut area = vec![f32::INFINITY; ps.len()];
+    let mut heap = BinaryHeap::new();
+    for i in a + 1..b {
+        area[i] = triangle_area(ps[i - 1], ps[i], ps[i + 1]);
+        heap.push(Candidate {
+            area: area[i],
+            index: i,
+        });
+        keep[i] = true;
+    }
+    while let Some(Candidate {
+        area: smallest,
+        index,
+    }) = heap.pop()
+    {
+        if !keep[index] || smallest != area[index] {
+            // Dropped already, or its area has changed since.
+            continue;
+        }
+        if smallest >= min_area {
+            break;
+        }
+        keep[index] = false;
+        let (p, n) = (prev[index], next[index]);
+        next[p] = n;
+        prev[n] = p;
+        // A neighbour's area never goes below the dropped one's, so points
+        // go in the order their own removal made them insignificant.
+        for i in [p, n] {
+            if i != a && i != b {
+                area[i] = triangle_area(ps[prev[i]], ps[i], ps[next[i]]).max(smallest);
+                heap.push(Candidate {
+                    area: area[i],
+                    index: i,
+                });
+            }
+        }
+    }
+}
+
+// The points of a line with the insignificant ones removed, as judged by
+// `options.method`. With Ramer-Douglas-Peucker every removed point is
+// within `options.tolerance` of the result. Visvalingam-Whyatt only
+// bounds the area of the triangle each removed point made, so gives no
+// such guarantee. The ends and any corners are kept.
+pub fn simplify_points<const N: usize>(
+    ps: &[Point<N>],
+    closed: bool,
+    options: &SimplifyOptions,
+) -> Vec<Point<N>> {
+    let mut ps = ps.to_vec();
+    ps.dedup();
+    if closed && ps.len() > 1 && ps[0] != ps[ps.len() - 1] {
+        ps.push(ps[0]);
+    }
+    let n = ps.len();
+    if n <= 2 {
+        return ps;
+    }
+
+    let mut keep = vec![false; n];
+    keep[0] = true;
+    keep[n - 1] = true;
+    let cos_corner = options.corner_angle.cos();
+    for i in 1..n - 1 {
+        let (u, v) = (ps[i] - ps[i - 1], ps[i + 1] - ps[i]);
+        if u.dot(v) < cos_corner * (u.norm_squared() * v.norm_squared()).sqrt() {
+            keep[i] = true;
+        }
+    }
+    if closed && keep.iter().filter(|k| **k).count() <= 2 {
+        // Without corners, a loop keeps the triangle that best fills it.
+        let furthest = |d: &dyn Fn(Point<N>) -> f32, keep: &[bool]| {
+            (1..n - 1)
+                .filter(|i| !keep[*i])
+                .max_by(|x, y| d(ps[*x]).total_cmp(&d(ps[*y])))
+        };
+        let far = furthest(&|p| distance(p, ps[0]), &keep).unwrap();
+        keep[far] = true;
+        if let Some(side) = furthest(&|p| segment_distance(p, ps[0], ps[far]), &keep) {
+            keep[side] = true;
+        }
+    }
+
+    let anchors: Vec<usize> = (0..n).filter(|i| keep[*i]).collect();
+    for w in anchors.windows(2) {
+        match options.method {
+            SimplifyMethod::RamerDouglasPeucker => {
+                ramer_douglas_peucker(&ps, w[0], w[1], options.tolerance, &mut keep)
+            }
+            SimplifyMethod::VisvalingamWhyatt => {
+                visvalingam_whyatt(&ps, w[0], w[1], options.tolerance, &mut keep)
+            }
+        }
+    }
+
+    let mut result: Vec<Point<N>> = (0..n).filter(|i| keep[*i]).map(|i| ps[i]).collect();
+    if closed {
+        result.pop();
+    }
+    result
+}
+
+impl<const N: usize, A> PolyLine<N, A>
+where
+    A: Clone,
+{
+    pub fn simplified(&self, options: &SimplifyOptions) -> PolyLine<N, A> {
+        PolyLine {
+            ps: simplify_points(&self.ps, self.closed, options),
+            attributes: self.attributes.clone(),
+            closed: self.closed,
+        }
+    }
+}
+
+impl<const N: usize> LineSet<N> {
+    pub fn simplified(&self, options: &SimplifyOptions) -> LineSet<N> {
+        LineSet {
+            lines: self.lines.iter().map(|l| l.simplified(options)).collect(),
+        }
+    }
+}
+
+// Pipeline stage that simplifies each polyline as it passes through,
+// e.g. to thin out the output of a `MegaMerger`.
+pub struct Simplifier<const N: usize> {
+    pub input: Receiver<PolyLine<N, ()>>,
+    pub output: Sender<PolyLine<N, ()>>,
+    pub options: SimplifyOptions,
+}
+
+impl<const N: usize> Simplifier<N> {
+    pub fn new(
+        input: Receiver<PolyLine<N, ()>>,
+        output: Sender<PolyLine<N, ()>>,
+        options: SimplifyOptions,
+    ) -> Simplifier<N> {
+        Simplifier {
+            input,
+            output,
+            options,
+        }
+    }
+
+    pub async fn run(mut self) -> Result<(), ()> {
+        while let Some(pl) = self.input.recv().await {
+            self.output
+                .send(pl.simplified(&self.options))
+                .await
+                .map_err(|_| ())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::n2::point::p2;
+
+    // A wobbly line along the x axis, with a right angle at (10, 0).
+    fn wobbly() -> Vec<Point<2>> {
+        let mut ps: Vec<Point<2>> = (0..=100)
+            .map(|i| p2(i as f32 * 0.1, 0.01 * (i as f32).sin()))
+            .collect();
+        ps.extend((1..=100).map(|i| p2(10.0, i as f32 * 0.1)));
+        ps
+    }
+
+    #[test]
+    pub fn simplify_keeps_ends_and_corners() {
+        for method in [
+            SimplifyMethod::RamerDouglasPeucker,
+            SimplifyMethod::VisvalingamWhyatt,
+        ] {
+            let options = SimplifyOptions {
+                method,
+                tolerance: 0.3,
+                ..Default::default()
+            };
+            let ps = simplify_points(&wobbly(), false, &options);
+            assert_eq!(ps.len(), 3);
+            assert_eq!(ps[0].vs, [0.0, 0.0]);
+            assert!(distance(ps[1], p2(10.0, 0.0)) < 0.02);
+            assert_eq!(ps[2].vs, [10.0, 10.0]);
+        }
+
+        // Without corners, a coarse loop comes down to a triangle.
+        let circle = PolyLine {
+            ps: (0..64)
+                .map(|i| {
+                    let a = i as f32 * std::f32::consts::TAU / 64.0;
+                    p2(a.cos(), a.sin())
+                })
+                .collect(),
+            attributes: (),
+            closed: true,
+        };
+        let coarse = circle.simplified(&SimplifyOptions {
+            tolerance: 2.0,
+            ..Default::default()
+        });
+        assert!(coarse.closed);
+        assert_eq!(coarse.ps.len(), 3);
+        let fine = circle.simplified(&SimplifyOptions {
+            tolerance: 0.01,
+            ..Default::default()
+        });
+        assert!(fine.ps.len() > 8 && fine.ps.len() < 64);
+    }
+}
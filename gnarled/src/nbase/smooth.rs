@@ -0,0 +1,154 @@
+use crate::nbase::cubic_bezier::CubicBezierPath;
+use crate::nbase::point::{distance, Point};
+use crate::nbase::polyline::PolyLine;
+
+impl<const N: usize, A> PolyLine<N, A> {
+    // The points with repeats removed, including the closing one, so
+    // that neighbouring points are always some distance apart.
+    fn distinct_points(&self) -> Vec<Point<N>> {
+        let mut ps = self.ps.clone();
+        ps.dedup();
+        if self.closed && ps.len() > 1 && ps[0] == ps[ps.len() - 1] {
+            ps.pop();
+        }
+        ps
+    }
+
+    // A smooth curve through every point of the line, using centripetal
+    // Catmull-Rom splines, which don't overshoot or form loops where the
+    // points are unevenly spaced. A closed line gives a path that ends
+    // back at its start.
+    pub fn catmull_rom(&self) -> CubicBezierPath<N> {
+        let ps = self.distinct_points();
+        let n = ps.len();
+        if n < 2 {
+            return CubicBezierPath { ps };
+        }
+        // The neighbours of open ends are reflections of the next point in.
+        let point = |i: isize| -> Point<N> {
+            if self.closed {
+                ps[i.rem_euclid(n as isize) as usize]
+            } else if i < 0 {
+                ps[0] * 2.0 - ps[1]
+            } else if i as usize >= n {
+                ps[n - 1] * 2.0 - ps[n - 2]
+            } else {
+                ps[i as usize]
+            }
+        };
+        let segments = if self.closed { n } else { n - 1 };
+        let mut result = vec![ps[0]];
+        for i in 0..segments as isize {
+            let (p0, p1, p2, p3) = (point(i - 1), point(i), point(i + 1), point(i + 2));
+            let (t01, t12, t23) = (
+                distance(p0, p1).sqrt(),
+                distance(p1, p2).sqrt(),
+                distance(p2, p3).sqrt(),
+            );
+            let m1 = ((p1 - p0) * (1.0 / t01) - (p2 - p0) * (1.0 / (t01 + t12))) * t12 + (p2 - p1);
+            let m2 = ((p3 - p2) * (1.0 / t23) - (p3 - p1) * (1.0 / (t12 + t23))) * t12 + (p2 - p1);
+            result.push(p1 + m1 * (1.0 / 3.0));
+            result.push(p2 - m2 * (1.0 / 3.0));
+            result.push(p2);
+        }
+        CubicBezierPath { ps: result }
+    }
+
+    // Rounds off the corners by repeatedly cutting a quarter of each
+    // segment off both its ends. The ends of an open line stay put.
+    pub fn chaikin(&self, iterations: usize) -> PolyLine<N, A>
+    where
+        A: Clone,
+    {
+        let mut ps = self.distinct_points();
+        for _ in 0..iterations {
+            let n = ps.len();
+            if n < 3 {
+                break;
+            }
+            let segments = if self.closed { n } else { n - 1 };
+            let mut cut = vec![];
+            if !self.closed {
+                cut.push(ps[0]);
+            }
+            for i in 0..segments {
+                let (a, b) = (ps[i], ps[(i + 1) % n]);
+                if self.closed || i > 0 {
+                    cut.push(Point::lerp(0.25, a, b));
+                }
+                if self.closed || i < segments - 1 {
+                    cut.push(Point::lerp(0.75, a, b));
+                }
+            }
+            if !self.closed {
+                cut.push(ps[n - 1]);
+            }
+            ps = cut;
+        }
+        PolyLine {
+            ps,
+            attributes: self.attributes.clone(),
+            closed: self.closed,
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::n2::point::p2;
+
+    fn zigzag(closed: bool) -> PolyLine<2, ()> {
+        PolyLine {
+            ps: vec![p2(0.0, 0.0), p2(1.0, 0.0), p2(5.0, 2.0), p2(6.0, 0.0)],
+            attributes: (),
+            closed,
+        }
+    }
+
+    #[test]
+    pub fn catmull_rom_through_points() {
+        for closed in [false, true] {
+            let line = zigzag(closed);
+            let path = line.catmull_rom();
+            let segments = if closed { 4 } else { 3 };
+            assert_eq!(path.ps.len(), 3 * segments + 1);
+            for (i, p) in line.path_points().iter().enumerate() {
+                assert_eq!(path.ps[3 * i].vs, p.vs);
+            }
+            // Smooth where the segments meet.
+            for i in 1..segments {
+                let (a, b) = (
+                    path.ps[3 * i] - path.ps[3 * i - 1],
+                    path.ps[3 * i + 1] - path.ps[3 * i],
+                );
+                assert!((a.vs[0] * b.vs[1] - a.vs[1] * b.vs[0]).abs() < 1e-4);
+                assert!(a.dot(b) > 0.0);
+            }
+        }
+
+        // Straight lines stay straight.
+        let straight = PolyLine {
+            ps: vec![p2(0.0, 0.0), p2(3.0, 0.0)],
+            attributes: (),
+            closed: false,
+        };
+        let path = straight.catmull_rom();
+        assert_eq!(path.ps.len(), 4);
+        assert!(path.ps.iter().all(|p| p.vs[1] == 0.0));
+    }
+
+    #[test]
+    pub fn chaikin_cuts_corners() {
+        let open = zigzag(false).chaikin(2);
+        assert_eq!(open.ps.len(), 10);
+        assert_eq!(open.ps[0].vs, [0.0, 0.0]);
+        assert_eq!(open.ps[9].vs, [6.0, 0.0]);
+        assert_eq!(open.ps[1].vs, [0.5625, 0.0]);
+
+        let closed = zigzag(true).chaikin(2);
+        assert!(closed.closed);
+        assert_eq!(closed.ps.len(), 16);
+        assert_eq!(closed.ps[0].vs, [0.375, 0.0]);
+    }
+}
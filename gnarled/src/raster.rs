@@ -0,0 +1,481 @@
+use crate::{
+    n2::{
+        bounds::Bounds, cubic_bezier::CubicBezierPath, lineset::LineSet, point::p2, point::Point,
+    },
+    nbase::{line_segment::LineSegment, polyline::PolyLine},
+};
+
+pub trait Rasterizable {
+    fn rasterize(&self, r: &mut Rasterizer);
+}
+
+// An RGB image, stored row by row from the top left.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Raster {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<[u8; 3]>,
+}
+
+// How much two rasters differ, per channel.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ImageDiff {
+    pub max_difference: u8,
+    pub mean_difference: f32,
+    pub differing_pixels: usize,
+}
+
+impl ImageDiff {
+    pub fn is_identical(&self) -> bool {
+        self.differing_pixels == 0
+    }
+}
+
+impl Raster {
+    // A white image.
+    pub fn new(width: usize, height: usize) -> Raster {
+        Raster {
+            width,
+            height,
+            pixels: vec![[255; 3]; width * height],
+        }
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> [u8; 3] {
+        self.pixels[y * self.width + x]
+    }
+
+    // None if the images are different sizes.
+    pub fn diff(&self, other: &Raster) -> Option<ImageDiff> {
+        if self.width != other.width || self.height != other.height {
+            return None;
+        }
+        let mut max_difference = 0;
+        let mut total = 0u64;
+        let mut differing_pixels = 0;
+        for (a, b) in self.pixels.iter().zip(other.pixels.iter()) {
+            let d = (0..3).map(|i| a[i].abs_diff(b[i])).max().unwrap();
+            max_difference = max_difference.max(d);
+            total += d as u64;
+            if d > 0 {
+                differing_pixels += 1;
+            }
+        }
+        Some(ImageDiff {
+            max_difference,
+            mean_difference: total as f32 / self.pixels.len().max(1) as f32,
+            differing_pixels,
+        })
+    }
+
+    // Binary PPM (P6).
+    pub fn write_ppm<W>(&self, w: &mut W) -> Result<(), std::io::Error>
+    where
+        W: std::io::Write,
+    {
+        write!(w, "P6\n{} {}\n255\n", self.width, self.height)?;
+        for p in &self.pixels {
+            w.write_all(p)?;
+        }
+        Ok(())
+    }
+
+    // Reads a binary PPM (P6) with a maxval of 255, as written by `write_ppm`.
+    pub fn read_ppm<R>(r: &mut R) -> Result<Raster, std::io::Error>
+    where
+        R: std::io::Read,
+    {
+        let mut data = vec![];
+        r.read_to_end(&mut data)?;
+        let invalid = || std::io::Error::new(std::io::ErrorKind::InvalidData, "invalid PPM");
+
+        // Header is four whitespace separated tokens, with optional comments.
+        let mut tokens = vec![];
+        let mut i = 0;
+        while tokens.len() < 4 {
+            while i < data.len() && data[i].is_ascii_whitespace() {
+                i += 1;
+            }
+            if i < data.len() && data[i] == b'#' {
+                while i < data.len() && data[i] != b'\n' {
+                    i += 1;
+                }
+                continue;
+            }
+            let start = i;
+            while i < data.len() && !data[i].is_ascii_whitespace() {
+                i += 1;
+            }
+            if start == i {
+                return Err(invalid());
+            }
+            tokens.push(std::str::from_utf8(&data[start..i]).map_err(|_| invalid())?);
+        }
+        // Exactly one whitespace character separates the header from the data.
+        i += 1;
+        let number = |s: &str| s.parse::<usize>().map_err(|_| invalid());
+        if tokens[0] != "P6" || number(tokens[3])? != 255 {
+            return Err(invalid());
+        }
+        let (width, height) = (number(tokens[1])?, number(tokens[2])?);
+        // Sizes from a bad header mustn't overflow.
+        let end = width
+            .checked_mul(height)
+            .and_then(|n| n.checked_mul(3))
+            .and_then(|n| n.checked_add(i))
+            .ok_or_else(invalid)?;
+        let body = data.get(i..end).ok_or_else(invalid)?;
+        Ok(Raster {
+            width,
+            height,
+            pixels: body.chunks(3).map(|c| [c[0], c[1], c[2]]).collect(),
+        })
+    }
+
+    // Uncompressed PNG. The files are large, but it needs no dependencies
+    // and any viewer can open them.
+    pub fn write_png<W>(&self, w: &mut W) -> Result<(), std::io::Error>
+    where
+        W: std::io::Write,
+    {
+        w.write_all(&[0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a])?;
+
+        let mut ihdr = vec![];
+        ihdr.extend_from_slice(&(self.width as u32).to_be_bytes());
+        ihdr.extend_from_slice(&(self.height as u32).to_be_bytes());
+        // 8 bit depth, RGB, default compression, filtering and no interlace
+        ihdr.extend_from_slice(&[8, 2, 0, 0, 0]);
+        write_png_chunk(w, b"IHDR", &ihdr)?;
+
+        // Each row is preceded by its filter type, 0 = None.
+        let mut raw = Vec::with_capacity(self.height * (self.width * 3 + 1));
+        for row in self.pixels.chunks(self.width.max(1)) {
+            raw.push(0);
+            for p in row {
+                raw.extend_from_slice(p);
+            }
+        }
+
+        // zlib stream made of "stored" deflate blocks.
+        let mut idat = vec![0x78, 0x01];
+        let blocks: Vec<&[u8]> = if raw.is_empty() {
+            vec![&[]]
+        } else {
+            raw.chunks(0xffff).collect()
+        };
+        for (i, block) in blocks.iter().enumerate() {
+            idat.push(if i + 1 == blocks.len() { 1 } else { 0 });
+            let len = block.len() as u16;
+            idat.extend_from_slice(&len.to_le_bytes());
+            idat.extend_from_slice(&(!len).to_le_bytes());
+            idat.extend_from_slice(block);
+        }
+        idat.extend_from_slice(&adler32(&raw).to_be_bytes());
+        write_png_chunk(w, b"IDAT", &idat)?;
+
+        write_png_chunk(w, b"IEND", &[])
+    }
+}
+
+fn write_png_chunk<W>(w: &mut W, kind: &[u8; 4], data: &[u8]) -> Result<(), std::io::Error>
+where
+    W: std::io::Write,
+{
+    w.write_all(&(data.len() as u32).to_be_bytes())?;
+    w.write_all(kind)?;
+    w.write_all(data)?;
+    let mut crc = crc32_update(0xffff_ffff, kind);
+    crc = crc32_update(crc, data);
+    w.write_all(&(crc ^ 0xffff_ffff).to_be_bytes())
+}
+
+fn crc32_update(mut crc: u32, data: &[u8]) -> u32 {
+    for &b in data {
+        crc ^= b as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                0xedb8_8320 ^ (crc >> 1)
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for &d in data {
+        a = (a + d as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+// Draws antialiased strokes into a `Raster`, mapping `view_box`
+// onto the whole image.
+pub struct Rasterizer {
+    pub raster: Raster,
+    pub view_box: Bounds,
+    // Stroke width, in view box units.
+    pub pen_width: f32,
+    pub colour: [u8; 3],
+    // Per-pixel coverage of the stroke being drawn, so that the
+    // overlapping ends of segments in a polyline aren't drawn twice.
+    coverage: Vec<f32>,
+    touched: Vec<usize>,
+}
+
+impl Rasterizer {
+    pub fn new(width: usize, height: usize, view_box: Bounds, pen_width: f32) -> Rasterizer {
+        Rasterizer {
+            raster: Raster::new(width, height),
+            view_box,
+            pen_width,
+            colour: [0, 0, 0],
+            coverage: vec![0.0; width * height],
+            touched: vec![],
+        }
+    }
+
+    pub fn add<T>(&mut self, item: &T)
+    where
+        T: Rasterizable,
+    {
+        item.rasterize(self)
+    }
+
+    pub fn into_raster(self) -> Raster {
+        self.raster
+    }
+
+    fn to_pixels(&self, p: Point) -> Point {
+        let size = self.view_box.max - self.view_box.min;
+        let scale = p2(
+            self.raster.width as f32 / size.vs[0],
+            self.raster.height as f32 / size.vs[1],
+        );
+        (p - self.view_box.min) * scale
+    }
+
+    // Draws a connected stroke through the points.
+    pub fn stroke(&mut self, ps: &[Point]) {
+        if ps.is_empty() {
+            return;
+        }
+        let size = self.view_box.max - self.view_box.min;
+        let pixel_scale =
+            (self.raster.width as f32 / size.vs[0]).min(self.raster.height as f32 / size.vs[1]);
+        let half_width = 0.5 * self.pen_width * pixel_scale;
+        let pixels: Vec<Point> = ps.iter().map(|p| self.to_pixels(*p)).collect();
+        if pixels.len() == 1 {
+            self.cover_segment(pixels[0], pixels[0], half_width);
+        }
+        for w in pixels.windows(2) {
+            self.cover_segment(w[0], w[1], half_width);
+        }
+        self.blend_stroke();
+    }
+
+    fn cover_segment(&mut self, a: Point, b: Point, half_width: f32) {
+        let (w, h) = (self.raster.width as i64, self.raster.height as i64);
+        let reach = half_width + 1.0;
+        let min = Point::componentwise_min(a, b);
+        let max = Point::componentwise_max(a, b);
+        let x0 = ((min.vs[0] - reach).floor() as i64).max(0);
+        let x1 = ((max.vs[0] + reach).ceil() as i64).min(w - 1);
+        let y0 = ((min.vs[1] - reach).floor() as i64).max(0);
+        let y1 = ((max.vs[1] + reach).ceil() as i64).min(h - 1);
+        let ab = b - a;
+        let ab2 = ab.norm_squared();
+        for y in y0..=y1 {
+            for x in x0..=x1 {
+                let c = p2(x as f32 + 0.5, y as f32 + 0.5);
+                let t = if ab2 > 0.0 {
+                    ((c - a).dot(ab) / ab2).clamp(0.0, 1.0)
+                } else {
+                    0.0
+                };
+                let d = (c - (a + ab * t)).norm_squared().sqrt();
+                let cov = (half_width + 0.5 - d).clamp(0.0, 1.0);
+                if cov > 0.0 {
+                    let i = (y * w + x) as usize;
+                    if self.coverage[i] == 0.0 {
+                        self.touched.push(i);
+                    }
+                    self.coverage[i] = self.coverage[i].max(cov);
+                }
+            }
+        }
+    }
+
+    fn blend_stroke(&mut self) {
+        for &i in &self.touched {
+            let cov = self.coverage[i];
+            let p = &mut self.raster.pixels[i];
+            for (v, c) in p.iter_mut().zip(self.colour) {
+                *v = (*v as f32 * (1.0 - cov) + c as f32 * cov).round() as u8;
+            }
+            self.coverage[i] = 0.0;
+        }
+        self.touched.clear();
+    }
+}
+
+impl<A> Rasterizable for PolyLine<2, A> {
+    fn rasterize(&self, r: &mut Rasterizer) {
+        r.stroke(&self.path_points())
+    }
+}
+
+impl<A> Rasterizable for LineSegment<2, A> {
+    fn rasterize(&self, r: &mut Rasterizer) {
+        r.stroke(&self.ps)
+    }
+}
+
+impl Rasterizable for LineSet {
+    fn rasterize(&self, r: &mut Rasterizer) {
+        for l in &self.lines {
+            l.rasterize(r);
+        }
+    }
+}
+
+impl Rasterizable for CubicBezierPath {
+    fn rasterize(&self, r: &mut Rasterizer) {
+        if self.ps.len() <= 1 {
+            return;
+        }
+        // A quarter of a pixel is too small to see.
+        let size = r.view_box.max - r.view_box.min;
+        let pixel = (size.vs[0] / r.raster.width as f32).min(size.vs[1] / r.raster.height as f32);
+        r.stroke(&self.flatten(0.25 * pixel).path_points())
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    fn view_box() -> Bounds {
+        Bounds {
+            min: p2(0.0, 0.0),
+            max: p2(100.0, 100.0),
+        }
+    }
+
+    #[test]
+    pub fn stroke_covers_expected_pixels() {
+        let mut r = Rasterizer::new(100, 100, view_box(), 2.0);
+        r.add(&PolyLine {
+            ps: vec![p2(10.0, 50.0), p2(90.0, 50.0)],
+            attributes: (),
+            closed: false,
+        });
+        let raster = r.into_raster();
+        assert_eq!(raster.get(50, 49), [0, 0, 0]);
+        assert_eq!(raster.get(50, 50), [0, 0, 0]);
+        assert_eq!(raster.get(50, 40), [255, 255, 255]);
+        assert_eq!(raster.get(5, 50), [255, 255, 255]);
+    }
+
+    #[test]
+    pub fn malformed_curves() {
+        let curve = CubicBezierPath {
+            ps: vec![
+                p2(10.0, 50.0),
+                p2(30.0, 50.0),
+                p2(70.0, 50.0),
+                p2(90.0, 50.0),
+                p2(90.0, 90.0),
+            ],
+        };
+        // The stray point after the segment is left out.
+        let mut r = Rasterizer::new(100, 100, view_box(), 2.0);
+        r.add(&curve);
+        let raster = r.into_raster();
+        assert_eq!(raster.get(50, 50), [0, 0, 0]);
+        assert_eq!(raster.get(90, 70), [255, 255, 255]);
+
+        // Nothing to see in an empty view box, but no hang or panic.
+        let empty = Bounds {
+            min: p2(0.0, 0.0),
+            max: p2(0.0, 0.0),
+        };
+        let mut r = Rasterizer::new(10, 10, empty, 2.0);
+        r.add(&curve);
+        r.add(&CubicBezierPath {
+            ps: vec![p2(0.0, 0.0); 3],
+        });
+    }
+
+    #[test]
+    pub fn diff_and_ppm_round_trip() {
+        let mut a = Rasterizer::new(40, 30, view_box(), 5.0);
+        a.add(&PolyLine {
+            ps: vec![p2(10.0, 10.0), p2(90.0, 90.0)],
+            attributes: (),
+            closed: false,
+        });
+        let a = a.into_raster();
+
+        let mut ppm = vec![];
+        a.write_ppm(&mut ppm).unwrap();
+        let b = Raster::read_ppm(&mut ppm.as_slice()).unwrap();
+        assert!(a.diff(&b).unwrap().is_identical());
+        // A header too big for the data, or to even multiply out.
+        for header in ["P6\n40 30\n255\n", "P6\n18446744073709551615 2\n255\n"] {
+            let e = Raster::read_ppm(&mut header.as_bytes()).unwrap_err();
+            assert_eq!(e.kind(), std::io::ErrorKind::InvalidData);
+        }
+
+        let c = Raster::new(40, 30);
+        let d = a.diff(&c).unwrap();
+        assert!(d.differing_pixels > 0);
+        assert_eq!(d.max_difference, 255);
+        assert!(a.diff(&Raster::new(10, 10)).is_none());
+    }
+
+    // Rasterizes a sample image and compares it with the reference copy
+    // in testdata. Set GNARLED_UPDATE_GOLDEN=1 to write a new reference
+    // after a deliberate change to the rasterizer.
+    #[test]
+    pub fn golden_sample_image() {
+        let svg = include_str!("../../sample_images/n3_01.svg");
+        let import = crate::svg_import::import_svg(svg).unwrap();
+        let mut r = Rasterizer::new(
+            160,
+            160,
+            Bounds {
+                min: p2(0.0, 0.0),
+                max: p2(800.0, 800.0),
+            },
+            2.0,
+        );
+        r.add(&import.line_set());
+        let raster = r.into_raster();
+
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/testdata/n3_01.ppm");
+        if std::env::var_os("GNARLED_UPDATE_GOLDEN").is_some() {
+            raster
+                .write_ppm(&mut std::fs::File::create(path).unwrap())
+                .unwrap();
+        }
+        let golden = Raster::read_ppm(&mut std::fs::File::open(path).unwrap()).unwrap();
+        let diff = raster.diff(&golden).unwrap();
+        // Allow for small changes in antialiasing, but not missing lines.
+        assert!(diff.mean_difference < 0.5, "{:?}", diff);
+        assert!(diff.differing_pixels < 160 * 160 / 100, "{:?}", diff);
+    }
+
+    #[test]
+    pub fn png_checksums() {
+        // Known values for the checksums used in the PNG writer.
+        assert_eq!(
+            crc32_update(0xffff_ffff, b"IEND") ^ 0xffff_ffff,
+            0xae42_6082
+        );
+        assert_eq!(adler32(b"Wikipedia"), 0x11e6_0398);
+    }
+}
@@ -0,0 +1,189 @@
+use tokio::sync::mpsc::Receiver;
+
+use crate::{
+    dxf::{DXFable, DxfWriter},
+    ebb::{EBBable, EbbWriter},
+    gcode::{GCodeable, GcodeWriter},
+    hpgl::{HPGLable, HpglWriter},
+    svg::{SVGable, SvgDocument},
+};
+
+// Something that drawings can be written to, e.g. an SVG document
+// or a plotter.
+pub trait Exporter<T> {
+    fn export(&mut self, item: &T) -> Result<(), std::io::Error>;
+    fn finish(&mut self) -> Result<(), std::io::Error>;
+}
+
+impl<W, T> Exporter<T> for SvgDocument<W>
+where
+    W: std::io::Write,
+    T: SVGable,
+{
+    fn export(&mut self, item: &T) -> Result<(), std::io::Error> {
+        self.add(item)
+    }
+
+    fn finish(&mut self) -> Result<(), std::io::Error> {
+        SvgDocument::finish(self)
+    }
+}
+
+impl<W, T> Exporter<T> for HpglWriter<W>
+where
+    W: std::io::Write,
+    T: HPGLable,
+{
+    fn export(&mut self, item: &T) -> Result<(), std::io::Error> {
+        self.add(item)
+    }
+
+    fn finish(&mut self) -> Result<(), std::io::Error> {
+        HpglWriter::finish(self)
+    }
+}
+
+impl<W, T> Exporter<T> for GcodeWriter<W>
+where
+    W: std::io::Write,
+    T: GCodeable,
+{
+    fn export(&mut self, item: &T) -> Result<(), std::io::Error> {
+        self.add(item)
+    }
+
+    fn finish(&mut self) -> Result<(), std::io::Error> {
+        GcodeWriter::finish(self)
+    }
+}
+
+impl<W, T> Exporter<T> for EbbWriter<W>
+where
+    W: std::io::Write,
+    T: EBBable,
+{
+    fn export(&mut self, item: &T) -> Result<(), std::io::Error> {
+        self.add(item)
+    }
+
+    fn finish(&mut self) -> Result<(), std::io::Error> {
+        EbbWriter::finish(self)
+    }
+}
+
+impl<W, T> Exporter<T> for DxfWriter<W>
+where
+    W: std::io::Write,
+    T: DXFable,
+{
+    fn export(&mut self, item: &T) -> Result<(), std::io::Error> {
+        self.add(item)
+    }
+
+    fn finish(&mut self) -> Result<(), std::io::Error> {
+        DxfWriter::finish(self)
+    }
+}
+
+#[derive(Debug)]
+pub enum SinkError {
+    // Writing failed after `written` items had been exported.
+    Io {
+        source: std::io::Error,
+        written: usize,
+    },
+}
+
+impl std::fmt::Display for SinkError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SinkError::Io { source, written } => {
+                write!(f, "export failed after {} items: {}", written, source)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SinkError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SinkError::Io { source, .. } => Some(source),
+        }
+    }
+}
+
+// The end of a pipeline: exports everything received (e.g. `PolyLine`s or
+// `LineSegment`s) until the channel closes.
+pub struct Sink<T, E> {
+    pub input: Receiver<T>,
+    pub exporter: E,
+}
+
+impl<T, E> Sink<T, E>
+where
+    E: Exporter<T>,
+{
+    pub fn new(input: Receiver<T>, exporter: E) -> Sink<T, E> {
+        Sink { input, exporter }
+    }
+
+    async fn drain(&mut self) -> Result<usize, SinkError> {
+        let mut written = 0;
+        while let Some(item) = self.input.recv().await {
+            self.exporter
+                .export(&item)
+                .map_err(|source| SinkError::Io { source, written })?;
+            written += 1;
+        }
+        Ok(written)
+    }
+
+    // Returns the exporter, unfinished, so more can be added to it.
+    pub async fn run(mut self) -> Result<E, SinkError> {
+        self.drain().await?;
+        Ok(self.exporter)
+    }
+
+    // Like `run`, but also finishes the exporter.
+    pub async fn run_to_end(mut self) -> Result<E, SinkError> {
+        let written = self.drain().await?;
+        self.exporter
+            .finish()
+            .map_err(|source| SinkError::Io { source, written })?;
+        Ok(self.exporter)
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::n2::{bounds::Bounds, point::p2};
+    use crate::nbase::line_segment::LineSegment;
+    use tokio::sync::mpsc::channel;
+
+    #[tokio::test]
+    pub async fn sink_exports_everything() {
+        let mut out = vec![];
+        let doc = SvgDocument::new(
+            &mut out,
+            Bounds {
+                min: p2(0.0, 0.0),
+                max: p2(10.0, 10.0),
+            },
+        )
+        .unwrap();
+        let (sender, receiver) = channel(10);
+        let producer = async move {
+            for i in 0..3 {
+                let x = i as f32;
+                let s: LineSegment<2, ()> = LineSegment::new(p2(x, 0.0), p2(x, 1.0));
+                sender.send(s).await.unwrap();
+            }
+        };
+        let (result, _) = tokio::join!(Sink::new(receiver, doc).run_to_end(), producer);
+        drop(result.unwrap());
+        let out = String::from_utf8(out).unwrap();
+        assert_eq!(out.matches("<path").count(), 3);
+        assert!(out.ends_with("</svg>\n"));
+    }
+}
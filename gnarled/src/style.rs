@@ -0,0 +1,274 @@
+use std::fmt::Display;
+
+use crate::attributes::{
+    AttributeDXFLayer, AttributeHPGLPen, AttributeRange, AttributeReverse, AttributeSVGLayer,
+    AttributeSVGLine,
+};
+use crate::dxf::DxfLayer;
+use crate::nbase::polyline::PolyLineAttribute;
+use crate::svg::{xml_escape, SvgLayer};
+
+// Pen colours in the order they're numbered: black is pen 1, red pen 2, etc.
+const PEN_COLOURS: [(&str, (u8, u8, u8)); 4] = [
+    ("black", (0, 0, 0)),
+    ("red", (255, 0, 0)),
+    ("green", (0, 128, 0)),
+    ("blue", (0, 0, 255)),
+];
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Colour {
+    // Any SVG/CSS colour name.
+    Named(String),
+    Rgb(u8, u8, u8),
+}
+
+impl Colour {
+    pub fn named(name: &str) -> Colour {
+        Colour::Named(name.to_string())
+    }
+
+    pub fn rgb(r: u8, g: u8, b: u8) -> Colour {
+        Colour::Rgb(r, g, b)
+    }
+
+    pub fn black() -> Colour {
+        Colour::named("black")
+    }
+
+    pub fn red() -> Colour {
+        Colour::named("red")
+    }
+
+    pub fn green() -> Colour {
+        Colour::named("green")
+    }
+
+    pub fn blue() -> Colour {
+        Colour::named("blue")
+    }
+
+    // Parses "#rgb" and "#rrggbb", anything else is taken to be a name.
+    pub fn parse(s: &str) -> Colour {
+        let s = s.trim();
+        if let Some(h) = s.strip_prefix('#').filter(|h| h.is_ascii()) {
+            let rgb: Option<Vec<u8>> = match h.len() {
+                3 => h
+                    .chars()
+                    .map(|c| c.to_digit(16).map(|v| v as u8 * 17))
+                    .collect(),
+                6 => (0..3)
+                    .map(|i| u8::from_str_radix(&h[2 * i..2 * i + 2], 16).ok())
+                    .collect(),
+                _ => None,
+            };
+            if let Some(&[r, g, b]) = rgb.as_deref() {
+                return Colour::Rgb(r, g, b);
+            }
+        }
+        Colour::Named(s.to_string())
+    }
+
+    // The nearest AutoCAD Colour Index for the standard pen colours,
+    // anything else is drawn in black.
+    pub fn aci(&self) -> i16 {
+        match self.pen_index() {
+            Some(2) => 1,
+            Some(3) => 3,
+            Some(4) => 5,
+            _ => 7,
+        }
+    }
+
+    // The pen number for the standard pen colours, see `PEN_COLOURS`.
+    pub fn pen_index(&self) -> Option<u32> {
+        PEN_COLOURS
+            .iter()
+            .position(|(name, rgb)| match self {
+                Colour::Named(n) => n.eq_ignore_ascii_case(name),
+                Colour::Rgb(r, g, b) => (*r, *g, *b) == *rgb,
+            })
+            .map(|i| i as u32 + 1)
+    }
+}
+
+impl Default for Colour {
+    fn default() -> Self {
+        Colour::black()
+    }
+}
+
+impl Display for Colour {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Colour::Named(name) => write!(f, "{}", name),
+            Colour::Rgb(r, g, b) => write!(f, "#{:02x}{:02x}{:02x}", r, g, b),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum LineCap {
+    Butt,
+    Round,
+    Square,
+}
+
+impl Display for LineCap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LineCap::Butt => write!(f, "butt"),
+            LineCap::Round => write!(f, "round"),
+            LineCap::Square => write!(f, "square"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum LineJoin {
+    Miter,
+    Round,
+    Bevel,
+}
+
+impl Display for LineJoin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LineJoin::Miter => write!(f, "miter"),
+            LineJoin::Round => write!(f, "round"),
+            LineJoin::Bevel => write!(f, "bevel"),
+        }
+    }
+}
+
+// How a line is drawn. Anything left as `None` (or empty) isn't written
+// out, so the SVG defaults (or inherited values) apply.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct StrokeStyle {
+    pub colour: Colour,
+    pub width: Option<f32>,
+    pub opacity: Option<f32>,
+    pub dash_array: Vec<f32>,
+    pub line_cap: Option<LineCap>,
+    pub line_join: Option<LineJoin>,
+    pub id: Option<String>,
+    pub class: Option<String>,
+    // Which pen to plot with, i.e. the AxiDraw layer or HPGL pen number.
+    // If not set, the standard pens are chosen by colour, anything else uses pen 1.
+    pub pen: Option<u32>,
+}
+
+impl StrokeStyle {
+    pub fn new(colour: Colour) -> StrokeStyle {
+        StrokeStyle {
+            colour,
+            ..Default::default()
+        }
+    }
+
+    pub fn pen(&self) -> u32 {
+        self.pen.or_else(|| self.colour.pen_index()).unwrap_or(1)
+    }
+
+    // The style as SVG presentation attributes.
+    pub fn svg_attributes(&self) -> String {
+        let mut result = format!("stroke=\"{}\"", xml_escape(&self.colour.to_string()));
+        if let Some(width) = self.width {
+            result += &format!(" stroke-width=\"{}\"", width);
+        }
+        if let Some(opacity) = self.opacity {
+            result += &format!(" stroke-opacity=\"{}\"", opacity);
+        }
+        if !self.dash_array.is_empty() {
+            let dashes: Vec<String> = self.dash_array.iter().map(|d| d.to_string()).collect();
+            result += &format!(" stroke-dasharray=\"{}\"", dashes.join(" "));
+        }
+        if let Some(cap) = self.line_cap {
+            result += &format!(" stroke-linecap=\"{}\"", cap);
+        }
+        if let Some(join) = self.line_join {
+            result += &format!(" stroke-linejoin=\"{}\"", join);
+        }
+        if let Some(id) = &self.id {
+            result += &format!(" id=\"{}\"", xml_escape(id));
+        }
+        if let Some(class) = &self.class {
+            result += &format!(" class=\"{}\"", xml_escape(class));
+        }
+        result
+    }
+}
+
+impl AttributeSVGLine for StrokeStyle {
+    fn stroke_style(&self) -> StrokeStyle {
+        self.clone()
+    }
+}
+
+impl AttributeSVGLayer for StrokeStyle {
+    fn svg_layer(&self) -> SvgLayer {
+        SvgLayer::new(self.pen(), &self.colour.to_string())
+    }
+}
+
+impl AttributeHPGLPen for StrokeStyle {
+    fn hpgl_pen(&self) -> u32 {
+        self.pen()
+    }
+}
+
+impl AttributeDXFLayer for StrokeStyle {
+    fn dxf_layer(&self) -> DxfLayer {
+        DxfLayer::new(&self.colour.to_string(), self.colour.aci())
+    }
+}
+
+impl AttributeReverse for StrokeStyle {
+    fn reverse(&self) -> Self {
+        self.clone()
+    }
+}
+
+impl AttributeRange for StrokeStyle {
+    fn range(&self, _t0: f32, _t1: f32) -> Self {
+        self.clone()
+    }
+}
+
+impl PolyLineAttribute for StrokeStyle {
+    type LineAttribute = StrokeStyle;
+    fn attribute_for_line_segment(&self, _index: usize) -> Self::LineAttribute {
+        self.clone()
+    }
+    fn poly_range(&self, _start: (usize, f32), _end: (usize, f32)) -> Self {
+        self.clone()
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    #[test]
+    pub fn colours_and_attributes() {
+        assert_eq!(Colour::parse("#f80"), Colour::Rgb(255, 136, 0));
+        assert_eq!(Colour::parse("#00ff00").to_string(), "#00ff00");
+        assert_eq!(Colour::parse("Red").pen_index(), Some(2));
+        assert_eq!(Colour::parse("orange").pen_index(), None);
+
+        assert_eq!(StrokeStyle::default().svg_attributes(), "stroke=\"black\"");
+        let style = StrokeStyle {
+            width: Some(0.5),
+            dash_array: vec![2.0, 1.0],
+            line_cap: Some(LineCap::Round),
+            class: Some("a&b".to_string()),
+            ..StrokeStyle::new(Colour::rgb(1, 2, 3))
+        };
+        assert_eq!(
+            style.svg_attributes(),
+            "stroke=\"#010203\" stroke-width=\"0.5\" stroke-dasharray=\"2 1\" \
+             stroke-linecap=\"round\" class=\"a&amp;b\""
+        );
+        assert_eq!(style.pen(), 1);
+    }
+}
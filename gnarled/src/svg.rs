@@ -1,99 +1,672 @@
+use std::collections::BTreeMap;
 use std::fmt::Display;
 
 use crate::{
-    attributes::AttributeSVGLine,
-    n2::{cubic_bezier::CubicBezierPath, lineset::LineSet},
+    attributes::{AttributeSVGLayer, AttributeSVGLine},
+    n2::{bounds::Bounds, cubic_bezier::CubicBezierPath, lineset::LineSet, point::Point},
     nbase::line_segment::LineSegment,
     nbase::polyline::PolyLine,
+    style::StrokeStyle,
 };
 
 pub trait SVGable {
-    fn to_svg<W>(&self, w: &mut W) -> Result<(), std::io::Error>
+    fn to_svg_with_options<W>(&self, w: &mut W, options: &SvgOptions) -> Result<(), std::io::Error>
     where
         W: std::io::Write;
+
+    fn to_svg<W>(&self, w: &mut W) -> Result<(), std::io::Error>
+    where
+        W: std::io::Write,
+    {
+        self.to_svg_with_options(w, &SvgOptions::default())
+    }
+
+    // The style of content drawn as a single stroke style, which can be
+    // merged with its neighbours into one `<path>`.
+    fn path_style(&self) -> Option<StrokeStyle> {
+        None
+    }
+
+    // Appends the path data of content that has a `path_style`.
+    fn add_to_path(&self, _path: &mut PathData) {}
 }
 
-impl CubicBezierPath {
-    pub fn to_svg_with_properties<W>(
-        &self,
-        w: &mut W,
-        props: PolyLineProperties,
-    ) -> Result<(), std::io::Error>
+// How path data is written. The defaults match the original, verbose,
+// output: absolute coordinates to two decimal places, one command per line.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SvgOptions {
+    // Decimal places for coordinates.
+    pub precision: usize,
+    // Use relative (lower case) commands.
+    pub relative: bool,
+    // Leave out a command letter when it repeats the previous one.
+    pub implicit_repeat: bool,
+    // Only write the whitespace needed to separate numbers.
+    pub minimal_whitespace: bool,
+    // Write consecutive content with the same style as a single
+    // `<path>` with multiple subpaths.
+    pub merge_paths: bool,
+}
+
+impl Default for SvgOptions {
+    fn default() -> Self {
+        SvgOptions {
+            precision: 2,
+            relative: false,
+            implicit_repeat: false,
+            minimal_whitespace: false,
+            merge_paths: false,
+        }
+    }
+}
+
+impl SvgOptions {
+    // Everything switched on, for the smallest files.
+    pub fn compact() -> SvgOptions {
+        SvgOptions {
+            precision: 2,
+            relative: true,
+            implicit_repeat: true,
+            minimal_whitespace: true,
+            merge_paths: true,
+        }
+    }
+}
+
+// The `d` attribute of a `<path>`, built up a command at a time.
+pub struct PathData {
+    options: SvgOptions,
+    data: String,
+    // The current point, as written, i.e. rounded to the output precision.
+    current: (f64, f64),
+    // Where the current subpath started, which closing it returns to.
+    start: (f64, f64),
+    // The command a bare set of coordinates would repeat.
+    implicit: Option<char>,
+}
+
+impl PathData {
+    pub fn new(options: &SvgOptions) -> PathData {
+        PathData {
+            options: *options,
+            data: String::new(),
+            current: (0.0, 0.0),
+            start: (0.0, 0.0),
+            implicit: None,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    pub fn move_to(&mut self, p: Point) {
+        self.command('M', &[p]);
+    }
+
+    pub fn line_to(&mut self, p: Point) {
+        self.command('L', &[p]);
+    }
+
+    pub fn cubic_to(&mut self, c1: Point, c2: Point, p: Point) {
+        self.command('C', &[c1, c2, p]);
+    }
+
+    // Draws a line back to the start of the current subpath.
+    pub fn close(&mut self) {
+        self.data
+            .push(if self.options.relative { 'z' } else { 'Z' });
+        if !self.options.minimal_whitespace {
+            self.data.push('\n');
+        }
+        self.current = self.start;
+        self.implicit = None;
+    }
+
+    // A value as it will be written, so relative coordinates are computed
+    // from what was actually written and rounding errors don't accumulate.
+    fn round(&self, v: f32) -> f64 {
+        format!("{:.*}", self.options.precision, v)
+            .parse()
+            .unwrap_or(v as f64)
+    }
+
+    fn command(&mut self, c: char, ps: &[Point]) {
+        let minimal = self.options.minimal_whitespace;
+        if !(self.options.implicit_repeat && self.implicit == Some(c)) {
+            let c = if self.options.relative {
+                c.to_ascii_lowercase()
+            } else {
+                c
+            };
+            self.data.push(c);
+            if !minimal {
+                self.data.push(' ');
+            }
+        }
+        // Relative coordinates are all relative to the start of the command.
+        let start = self.current;
+        for (i, p) in ps.iter().enumerate() {
+            let (x, y) = (self.round(p.vs[0]), self.round(p.vs[1]));
+            let (dx, dy) = if self.options.relative {
+                (x - start.0, y - start.1)
+            } else {
+                (x, y)
+            };
+            if i > 0 && !minimal {
+                self.data.push_str(", ");
+            }
+            self.number(dx);
+            if !minimal {
+                self.data.push(if c == 'C' { ' ' } else { ',' });
+            }
+            self.number(dy);
+            self.current = (x, y);
+        }
+        if !minimal {
+            self.data.push('\n');
+        }
+        if c == 'M' {
+            self.start = self.current;
+        }
+        // Coordinates following a move are lines.
+        self.implicit = Some(if c == 'M' { 'L' } else { c });
+    }
+
+    fn number(&mut self, v: f64) {
+        let s = format!("{:.*}", self.options.precision, v);
+        if !self.options.minimal_whitespace {
+            self.data.push_str(&s);
+            return;
+        }
+        let mut s = s.as_str();
+        if s.contains('.') {
+            s = s.trim_end_matches('0').trim_end_matches('.');
+        }
+        let (sign, digits) = match s.strip_prefix('-') {
+            Some(d) => ("-", d),
+            None => ("", s),
+        };
+        let digits = match digits.strip_prefix('0') {
+            Some(d) if d.starts_with('.') => d,
+            _ => digits,
+        };
+        let sign = if digits == "0" { "" } else { sign };
+        // A minus sign separates numbers by itself.
+        if sign.is_empty()
+            && self
+                .data
+                .ends_with(|c: char| c.is_ascii_digit() || c == '.')
+        {
+            self.data.push(' ');
+        }
+        self.data.push_str(sign);
+        self.data.push_str(digits);
+    }
+}
+
+fn write_path<W>(w: &mut W, style: &StrokeStyle, path: &PathData) -> Result<(), std::io::Error>
+where
+    W: std::io::Write,
+{
+    if path.is_empty() {
+        return Ok(());
+    }
+    write_path_start(w, style)?;
+    if !path.options.minimal_whitespace {
+        writeln!(w)?;
+    }
+    w.write_all(path.data.as_bytes())?;
+    writeln!(w, r#""/>"#)
+}
+
+// A `<path>` still being added to when merging paths.
+#[derive(Default)]
+struct PendingPath(Option<(StrokeStyle, PathData)>);
+
+impl PendingPath {
+    fn add<W, T>(&mut self, w: &mut W, item: &T, options: &SvgOptions) -> Result<(), std::io::Error>
     where
         W: std::io::Write,
+        T: SVGable,
     {
-        if self.ps.len() <= 1 {
-            return Ok(());
+        if options.merge_paths {
+            if let Some(style) = item.path_style() {
+                if !matches!(&self.0, Some((s, _)) if *s == style) {
+                    self.flush(w)?;
+                    self.0 = Some((style, PathData::new(options)));
+                }
+                if let Some((_, path)) = &mut self.0 {
+                    item.add_to_path(path);
+                }
+                return Ok(());
+            }
+        }
+        self.flush(w)?;
+        item.to_svg_with_options(w, options)
+    }
+
+    fn write<W>(&self, w: &mut W) -> Result<(), std::io::Error>
+    where
+        W: std::io::Write,
+    {
+        match &self.0 {
+            Some((style, path)) => write_path(w, style, path),
+            None => Ok(()),
+        }
+    }
+
+    fn flush<W>(&mut self, w: &mut W) -> Result<(), std::io::Error>
+    where
+        W: std::io::Write,
+    {
+        self.write(w)?;
+        self.0 = None;
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PageUnit {
+    Millimeters,
+    Inches,
+}
+
+impl Display for PageUnit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PageUnit::Millimeters => write!(f, "mm"),
+            PageUnit::Inches => write!(f, "in"),
+        }
+    }
+}
+
+// Physical size of the output page.
+#[derive(Clone, Copy, Debug)]
+pub struct PageSize {
+    pub width: f32,
+    pub height: f32,
+    pub unit: PageUnit,
+}
+
+impl PageSize {
+    pub fn mm(width: f32, height: f32) -> PageSize {
+        PageSize {
+            width,
+            height,
+            unit: PageUnit::Millimeters,
+        }
+    }
+
+    pub fn inches(width: f32, height: f32) -> PageSize {
+        PageSize {
+            width,
+            height,
+            unit: PageUnit::Inches,
+        }
+    }
+}
+
+// An SVG file being written to `w`.
+//
+// The header is written on creation, and the closing tag is written
+// either by `finish` or, if that was never called, when the document
+// is dropped. This means an error part way through a pipeline still
+// leaves a well-formed (if incomplete) file behind.
+pub struct SvgDocument<W>
+where
+    W: std::io::Write,
+{
+    w: W,
+    options: SvgOptions,
+    pending: PendingPath,
+    finished: bool,
+}
+
+impl<W> SvgDocument<W>
+where
+    W: std::io::Write,
+{
+    pub fn new(w: W, view_box: Bounds) -> Result<SvgDocument<W>, std::io::Error> {
+        Self::create(w, None, view_box)
+    }
+
+    pub fn with_page_size(
+        w: W,
+        page: PageSize,
+        view_box: Bounds,
+    ) -> Result<SvgDocument<W>, std::io::Error> {
+        Self::create(w, Some(page), view_box)
+    }
+
+    fn create(
+        mut w: W,
+        page: Option<PageSize>,
+        view_box: Bounds,
+    ) -> Result<SvgDocument<W>, std::io::Error> {
+        let size = view_box.max - view_box.min;
+        write!(
+            w,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" xmlns:inkscape="http://www.inkscape.org/namespaces/inkscape""#
+        )?;
+        if let Some(page) = page {
+            write!(
+                w,
+                r#" width="{}{}" height="{}{}""#,
+                page.width, page.unit, page.height, page.unit
+            )?;
         }
-        assert!(self.ps.len() % 3 == 1);
         writeln!(
             w,
-            r#"<path stroke="{}" fill="transparent" d=""#,
-            props.stroke
+            r#" viewBox="{} {} {} {}">"#,
+            view_box.min.vs[0], view_box.min.vs[1], size.vs[0], size.vs[1]
         )?;
-        writeln!(w, "M {:.2},{:.2}", self.ps[0].vs[0], self.ps[0].vs[1])?;
-        for n in 0..(self.ps.len() - 1) / 3 {
-            if let [_x, c1, c2, y] = self.ps[3 * n..3 * n + 4] {
-                writeln!(
-                    w,
-                    "C {:.2} {:.2}, {:.2} {:.2}, {:.2} {:.2}",
-                    c1.vs[0], c1.vs[1], c2.vs[0], c2.vs[1], y.vs[0], y.vs[1]
-                )?;
-            }
+        Ok(SvgDocument {
+            w,
+            options: SvgOptions::default(),
+            pending: PendingPath::default(),
+            finished: false,
+        })
+    }
+
+    pub fn add<T>(&mut self, item: &T) -> Result<(), std::io::Error>
+    where
+        T: SVGable,
+    {
+        if self.finished {
+            return Err(std::io::Error::other(
+                "SVG document has already been finished",
+            ));
         }
-        writeln!(w, r#""/>"#)?;
+        self.pending.add(&mut self.w, item, &self.options)
+    }
 
+    // Applies to content added from now on.
+    pub fn set_options(&mut self, options: SvgOptions) {
+        self.options = options;
+    }
+
+    // Access to the underlying writer, for content that isn't `SVGable`.
+    pub fn writer(&mut self) -> Result<&mut W, std::io::Error> {
+        self.pending.flush(&mut self.w)?;
+        Ok(&mut self.w)
+    }
+
+    // Writes the closing tag. Calling this more than once is harmless.
+    pub fn finish(&mut self) -> Result<(), std::io::Error> {
+        if self.finished {
+            return Ok(());
+        }
+        self.pending.flush(&mut self.w)?;
+        writeln!(self.w, "</svg>")?;
+        self.w.flush()?;
+        // Only now, so that a failed finish is retried on drop.
+        self.finished = true;
         Ok(())
     }
 }
 
-impl SVGable for CubicBezierPath {
-    fn to_svg<W>(&self, w: &mut W) -> Result<(), std::io::Error>
+// An Inkscape layer. The AxiDraw software plots layers in order and
+// can pause between them, so we use one layer per pen. The label is
+// "<index> <name>", e.g. "1 black", which is what AxiDraw expects.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SvgLayer {
+    pub index: u32,
+    pub name: String,
+}
+
+impl SvgLayer {
+    pub fn new(index: u32, name: &str) -> SvgLayer {
+        SvgLayer {
+            index,
+            name: name.to_string(),
+        }
+    }
+
+    pub fn label(&self) -> String {
+        format!("{} {}", self.index, self.name)
+    }
+}
+
+#[derive(Default)]
+struct LayerContent {
+    data: Vec<u8>,
+    pending: PendingPath,
+}
+
+// Collects content into named layers, which are written out
+// as groups ordered by layer index.
+//
+// Content is encoded as it is added, using the layers' own options.
+//
+// There is one group per index, as that is both its id and the pen
+// AxiDraw uses for it. Content for a layer with the same index as one
+// already added, but another name, goes into the existing group.
+#[derive(Default)]
+pub struct SvgLayers {
+    layers: BTreeMap<u32, (SvgLayer, LayerContent)>,
+    options: SvgOptions,
+}
+
+impl SvgLayers {
+    pub fn new() -> SvgLayers {
+        SvgLayers::default()
+    }
+
+    pub fn with_options(options: SvgOptions) -> SvgLayers {
+        SvgLayers {
+            layers: BTreeMap::new(),
+            options,
+        }
+    }
+
+    fn content(&mut self, layer: &SvgLayer) -> &mut LayerContent {
+        &mut self
+            .layers
+            .entry(layer.index)
+            .or_insert_with(|| (layer.clone(), LayerContent::default()))
+            .1
+    }
+
+    // Raw access to the content of a layer, creating it if needed.
+    pub fn writer(&mut self, layer: &SvgLayer) -> &mut Vec<u8> {
+        let content = self.content(layer);
+        content
+            .pending
+            .flush(&mut content.data)
+            .expect("writing to a Vec can't fail");
+        &mut content.data
+    }
+
+    pub fn add<T>(&mut self, layer: &SvgLayer, item: &T) -> Result<(), std::io::Error>
+    where
+        T: SVGable,
+    {
+        let options = self.options;
+        let content = self.content(layer);
+        content.pending.add(&mut content.data, item, &options)
+    }
+
+    // Adds a polyline to the layer given by its attributes.
+    pub fn add_routed<A>(&mut self, line: &PolyLine<2, A>) -> Result<(), std::io::Error>
+    where
+        A: AttributeSVGLine + AttributeSVGLayer,
+    {
+        let layer = line.attributes.svg_layer();
+        self.add(&layer, line)
+    }
+
+    // Adds the lines to the layer given by `props`, drawn in its style.
+    pub fn add_routed_lines<A>(&mut self, lines: &LineSet, props: &A) -> Result<(), std::io::Error>
+    where
+        A: AttributeSVGLine + AttributeSVGLayer,
+    {
+        let (layer, style) = (props.svg_layer(), props.stroke_style());
+        for line in &lines.lines {
+            self.add(&layer, &line.map_attribute(|_| style.clone()))?;
+        }
+        Ok(())
+    }
+
+    // Adds the path to the layer given by `props`, drawn in its style.
+    pub fn add_routed_bezier<A>(
+        &mut self,
+        path: &CubicBezierPath,
+        props: &A,
+    ) -> Result<(), std::io::Error>
+    where
+        A: AttributeSVGLine + AttributeSVGLayer,
+    {
+        let layer = props.svg_layer();
+        self.add(&layer, &StyledBezier(path, props.stroke_style()))
+    }
+
+    pub fn layers(&self) -> impl Iterator<Item = &SvgLayer> {
+        self.layers.values().map(|(layer, _)| layer)
+    }
+}
+
+impl SVGable for SvgLayers {
+    fn to_svg_with_options<W>(&self, w: &mut W, _options: &SvgOptions) -> Result<(), std::io::Error>
     where
         W: std::io::Write,
     {
-        self.to_svg_with_properties(w, PolyLineProperties::default())
+        for (layer, content) in self.layers.values() {
+            writeln!(
+                w,
+                r#"<g inkscape:groupmode="layer" inkscape:label="{}" id="layer{}">"#,
+                xml_escape(&layer.label()),
+                layer.index
+            )?;
+            w.write_all(&content.data)?;
+            content.pending.write(w)?;
+            writeln!(w, "</g>")?;
+        }
+        Ok(())
     }
 }
 
-#[derive(Clone, Copy)]
-pub enum PolyLineStroke {
-    Black,
-    Red,
-    Green,
+pub(crate) fn xml_escape(s: &str) -> String {
+    let mut result = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => result.push_str("&amp;"),
+            '<' => result.push_str("&lt;"),
+            '>' => result.push_str("&gt;"),
+            '"' => result.push_str("&quot;"),
+            '\'' => result.push_str("&apos;"),
+            c => result.push(c),
+        }
+    }
+    result
 }
 
-impl Default for PolyLineStroke {
-    fn default() -> Self {
-        PolyLineStroke::Black
+impl<W> Drop for SvgDocument<W>
+where
+    W: std::io::Write,
+{
+    fn drop(&mut self) {
+        // Errors can't be reported from drop, call finish to see them.
+        let _ = self.finish();
     }
 }
 
-impl Display for PolyLineStroke {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            PolyLineStroke::Black => write!(f, "black"),
-            PolyLineStroke::Red => write!(f, "red"),
-            PolyLineStroke::Green => write!(f, "green"),
+// The opening of a `<path>` element, up to the start of its path data.
+fn write_path_start<W>(w: &mut W, style: &StrokeStyle) -> Result<(), std::io::Error>
+where
+    W: std::io::Write,
+{
+    write!(
+        w,
+        r#"<path {} fill="transparent" d=""#,
+        style.svg_attributes()
+    )
+}
+
+impl CubicBezierPath {
+    pub fn to_svg_with_properties<W, A>(&self, w: &mut W, props: &A) -> Result<(), std::io::Error>
+    where
+        W: std::io::Write,
+        A: AttributeSVGLine,
+    {
+        let mut path = PathData::new(&SvgOptions::default());
+        self.add_to_path(&mut path);
+        write_path(w, &props.stroke_style(), &path)
+    }
+}
+
+impl SVGable for CubicBezierPath {
+    fn to_svg_with_options<W>(&self, w: &mut W, options: &SvgOptions) -> Result<(), std::io::Error>
+    where
+        W: std::io::Write,
+    {
+        let mut path = PathData::new(options);
+        self.add_to_path(&mut path);
+        write_path(w, &StrokeStyle::default(), &path)
+    }
+
+    fn path_style(&self) -> Option<StrokeStyle> {
+        Some(StrokeStyle::default())
+    }
+
+    fn add_to_path(&self, path: &mut PathData) {
+        if self.ps.len() <= 1 {
+            return;
+        }
+        assert!(self.ps.len() % 3 == 1);
+        path.move_to(self.ps[0]);
+        for n in 0..(self.ps.len() - 1) / 3 {
+            if let [_x, c1, c2, y] = self.ps[3 * n..3 * n + 4] {
+                path.cubic_to(c1, c2, y);
+            }
         }
     }
 }
 
-#[derive(Clone, Copy, Default)]
-pub struct PolyLineProperties {
-    pub stroke: PolyLineStroke,
+// A path drawn in a style of its own rather than the default.
+struct StyledBezier<'a>(&'a CubicBezierPath, StrokeStyle);
+
+impl SVGable for StyledBezier<'_> {
+    fn to_svg_with_options<W>(&self, w: &mut W, options: &SvgOptions) -> Result<(), std::io::Error>
+    where
+        W: std::io::Write,
+    {
+        let mut path = PathData::new(options);
+        self.0.add_to_path(&mut path);
+        write_path(w, &self.1, &path)
+    }
+
+    fn path_style(&self) -> Option<StrokeStyle> {
+        Some(self.1.clone())
+    }
+
+    fn add_to_path(&self, path: &mut PathData) {
+        self.0.add_to_path(path)
+    }
+}
+
+fn add_polyline_to_path(path: &mut PathData, ps: &[Point], closed: bool) {
+    if ps.len() <= 1 {
+        return;
+    }
+    path.move_to(ps[0]);
+    for pp in &ps[1..] {
+        path.line_to(*pp);
+    }
+    if closed {
+        path.close();
+    }
 }
 
 impl PolyLine<2, ()> {
-    pub fn to_svg_with_properties<W>(
-        &self,
-        w: &mut W,
-        props: PolyLineProperties,
-    ) -> Result<(), std::io::Error>
+    pub fn to_svg_with_properties<W, A>(&self, w: &mut W, props: &A) -> Result<(), std::io::Error>
     where
         W: std::io::Write,
+        A: AttributeSVGLine,
     {
-        self.map_attribute(|_| props).to_svg(w)
+        let mut path = PathData::new(&SvgOptions::default());
+        add_polyline_to_path(&mut path, &self.ps, self.closed);
+        write_path(w, &props.stroke_style(), &path)
     }
 }
 
@@ -101,72 +674,74 @@ impl<A> SVGable for PolyLine<2, A>
 where
     A: AttributeSVGLine,
 {
-    fn to_svg<W>(&self, w: &mut W) -> Result<(), std::io::Error>
+    fn to_svg_with_options<W>(&self, w: &mut W, options: &SvgOptions) -> Result<(), std::io::Error>
     where
         W: std::io::Write,
     {
-        if self.ps.len() <= 1 {
-            return Ok(());
-        }
-        writeln!(
-            w,
-            r#"<path {} fill="transparent" d=""#,
-            self.attributes.line_attributes()
-        )?;
-        writeln!(w, "M {:.2},{:.2}", self.ps[0].vs[0], self.ps[0].vs[1])?;
-        for pp in &self.ps[1..] {
-            writeln!(w, "L {:.2},{:.2}", pp.vs[0], pp.vs[1])?;
-        }
-        writeln!(w, r#""/>"#)?;
+        let mut path = PathData::new(options);
+        self.add_to_path(&mut path);
+        write_path(w, &self.attributes.stroke_style(), &path)
+    }
 
-        Ok(())
+    fn path_style(&self) -> Option<StrokeStyle> {
+        Some(self.attributes.stroke_style())
+    }
+
+    fn add_to_path(&self, path: &mut PathData) {
+        add_polyline_to_path(path, &self.ps, self.closed)
     }
 }
 
 impl LineSet {
-    pub fn to_svg_with_properties<W>(
-        &self,
-        w: &mut W,
-        props: PolyLineProperties,
-    ) -> Result<(), std::io::Error>
+    pub fn to_svg_with_properties<W, A>(&self, w: &mut W, props: &A) -> Result<(), std::io::Error>
     where
         W: std::io::Write,
+        A: AttributeSVGLine,
     {
+        let style = props.stroke_style();
         for line in &self.lines {
-            line.to_svg_with_properties(w, props)?;
+            let mut path = PathData::new(&SvgOptions::default());
+            add_polyline_to_path(&mut path, &line.ps, line.closed);
+            write_path(w, &style, &path)?;
         }
         Ok(())
     }
 }
 
 impl SVGable for LineSet {
-    fn to_svg<W>(&self, w: &mut W) -> Result<(), std::io::Error>
+    fn to_svg_with_options<W>(&self, w: &mut W, options: &SvgOptions) -> Result<(), std::io::Error>
     where
         W: std::io::Write,
     {
+        if options.merge_paths {
+            let mut path = PathData::new(options);
+            self.add_to_path(&mut path);
+            return write_path(w, &StrokeStyle::default(), &path);
+        }
         for l in &self.lines {
-            l.to_svg(w)?
+            l.to_svg_with_options(w, options)?
         }
         Ok(())
     }
+
+    fn path_style(&self) -> Option<StrokeStyle> {
+        Some(StrokeStyle::default())
+    }
+
+    fn add_to_path(&self, path: &mut PathData) {
+        for l in &self.lines {
+            add_polyline_to_path(path, &l.ps, l.closed);
+        }
+    }
 }
 
 impl LineSegment<2, ()> {
-    pub fn to_svg_with_properties<W>(
-        &self,
-        w: &mut W,
-        props: PolyLineProperties,
-    ) -> Result<(), std::io::Error>
+    pub fn to_svg_with_properties<W, A>(&self, w: &mut W, props: &A) -> Result<(), std::io::Error>
     where
         W: std::io::Write,
+        A: AttributeSVGLine,
     {
-        self.map_attribute(|_| props).to_svg(w)
-    }
-}
-
-impl AttributeSVGLine for PolyLineProperties {
-    fn line_attributes(&self) -> String {
-        return format!("stroke=\"{}\"", self.stroke);
+        self.map_attribute(|_| props.stroke_style()).to_svg(w)
     }
 }
 
@@ -174,18 +749,216 @@ impl<A> SVGable for LineSegment<2, A>
 where
     A: AttributeSVGLine,
 {
-    fn to_svg<W>(&self, w: &mut W) -> Result<(), std::io::Error>
+    fn to_svg_with_options<W>(&self, w: &mut W, options: &SvgOptions) -> Result<(), std::io::Error>
     where
         W: std::io::Write,
     {
-        write!(
-            w,
-            r#"<path {} fill="transparent" d=""#,
-            self.attributes.line_attributes()
-        )?;
-        write!(w, "M {:.2},{:.2} ", self.ps[0].vs[0], self.ps[0].vs[1])?;
-        write!(w, "L {:.2},{:.2}", self.ps[1].vs[0], self.ps[1].vs[1])?;
-        writeln!(w, r#""/>"#)?;
-        Ok(())
+        let mut path = PathData::new(options);
+        self.add_to_path(&mut path);
+        write_path(w, &self.attributes.stroke_style(), &path)
+    }
+
+    fn path_style(&self) -> Option<StrokeStyle> {
+        Some(self.attributes.stroke_style())
+    }
+
+    fn add_to_path(&self, path: &mut PathData) {
+        add_polyline_to_path(path, &self.ps, false)
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::n2::point::p2;
+
+    fn lines() -> LineSet {
+        LineSet {
+            lines: vec![
+                PolyLine {
+                    ps: vec![p2(10.0, 10.0), p2(20.0, 10.0), p2(20.5, 9.25)],
+                    attributes: (),
+                    closed: false,
+                },
+                PolyLine {
+                    ps: vec![p2(0.0, 0.0), p2(1.0, 1.0)],
+                    attributes: (),
+                    closed: false,
+                },
+            ],
+        }
+    }
+
+    #[test]
+    pub fn default_path_output() {
+        let mut out = vec![];
+        lines().lines[1].to_svg(&mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "<path stroke=\"black\" fill=\"transparent\" d=\"\nM 0.00,0.00\nL 1.00,1.00\n\"/>\n"
+        );
+    }
+
+    #[test]
+    pub fn closed_path_output() {
+        let mut square = lines().lines[0].clone();
+        square.closed = true;
+        let mut out = vec![];
+        square
+            .to_svg_with_options(&mut out, &SvgOptions::compact())
+            .unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("d=\"m10 10 10 0 .5-.75z\""), "{}", out);
+        let import = crate::svg_import::import_svg(&format!("<svg>{}</svg>", out)).unwrap();
+        assert!(import.lines[0].closed);
+        assert_eq!(import.lines[0].ps.len(), 3);
+    }
+
+    fn view_box() -> Bounds {
+        Bounds {
+            min: p2(-10.0, 0.0),
+            max: p2(90.0, 50.0),
+        }
+    }
+
+    #[test]
+    pub fn document_header_and_footer() {
+        let header = "<svg xmlns=\"http://www.w3.org/2000/svg\" \
+            xmlns:inkscape=\"http://www.inkscape.org/namespaces/inkscape\"";
+
+        let mut out = vec![];
+        let mut doc = SvgDocument::new(&mut out, view_box()).unwrap();
+        doc.finish().unwrap();
+        doc.finish().unwrap();
+        assert!(doc.add(&lines()).is_err());
+        drop(doc);
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            format!("{} viewBox=\"-10 0 100 50\">\n</svg>\n", header)
+        );
+
+        // Dropped without finishing, with a page size.
+        let mut out = vec![];
+        {
+            let mut doc =
+                SvgDocument::with_page_size(&mut out, PageSize::mm(200.0, 100.0), view_box())
+                    .unwrap();
+            doc.add(&lines().lines[1]).unwrap();
+        }
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            format!(
+                "{} width=\"200mm\" height=\"100mm\" viewBox=\"-10 0 100 50\">\n\
+                <path stroke=\"black\" fill=\"transparent\" d=\"\nM 0.00,0.00\nL 1.00,1.00\n\"/>\n\
+                </svg>\n",
+                header
+            )
+        );
+    }
+
+    // Fails every write until it is allowed to succeed.
+    struct Failing<'a>(&'a std::cell::Cell<bool>, Vec<u8>);
+
+    impl std::io::Write for Failing<'_> {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            if self.0.get() {
+                self.1.write(buf)
+            } else {
+                Err(std::io::Error::other("not yet"))
+            }
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    pub fn failed_finish_is_retried() {
+        let ok = std::cell::Cell::new(true);
+        let mut doc = SvgDocument::new(Failing(&ok, vec![]), view_box()).unwrap();
+        ok.set(false);
+        assert!(doc.finish().is_err());
+        ok.set(true);
+        doc.finish().unwrap();
+        assert!(String::from_utf8(doc.w.1.clone())
+            .unwrap()
+            .ends_with(">\n</svg>\n"));
+    }
+
+    #[test]
+    pub fn layer_grouping_and_order() {
+        use crate::style::Colour;
+
+        let red = StrokeStyle::new(Colour::red());
+        let black = StrokeStyle::new(Colour::black());
+        // Not a pen colour, so it shares pen 1 with black.
+        let orange = StrokeStyle::new(Colour::Named("orange".to_string()));
+        let mut layers = SvgLayers::with_options(SvgOptions::compact());
+        layers
+            .add_routed(&lines().lines[1].map_attribute(|_| red.clone()))
+            .unwrap();
+        layers.add_routed_lines(&lines(), &black).unwrap();
+        layers
+            .add_routed_bezier(
+                &CubicBezierPath {
+                    ps: vec![p2(0.0, 0.0), p2(1.0, 0.0), p2(2.0, 1.0), p2(2.0, 2.0)],
+                },
+                &orange,
+            )
+            .unwrap();
+        layers
+            .add_routed_bezier(
+                &CubicBezierPath {
+                    ps: vec![p2(5.0, 5.0)],
+                },
+                &red,
+            )
+            .unwrap();
+        assert_eq!(
+            layers.layers().cloned().collect::<Vec<_>>(),
+            vec![SvgLayer::new(1, "black"), SvgLayer::new(2, "red")]
+        );
+
+        let mut out = vec![];
+        layers.to_svg(&mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "<g inkscape:groupmode=\"layer\" inkscape:label=\"1 black\" id=\"layer1\">\n\
+            <path stroke=\"black\" fill=\"transparent\" d=\"m10 10 10 0 .5-.75m-20.5-9.25 1 1\"/>\n\
+            <path stroke=\"orange\" fill=\"transparent\" d=\"m0 0c1 0 2 1 2 2\"/>\n\
+            </g>\n\
+            <g inkscape:groupmode=\"layer\" inkscape:label=\"2 red\" id=\"layer2\">\n\
+            <path stroke=\"red\" fill=\"transparent\" d=\"m0 0 1 1\"/>\n\
+            </g>\n"
+        );
+    }
+
+    #[test]
+    pub fn compact_path_output() {
+        let mut out = vec![];
+        {
+            let mut doc = SvgDocument::new(
+                &mut out,
+                Bounds {
+                    min: p2(0.0, 0.0),
+                    max: p2(100.0, 100.0),
+                },
+            )
+            .unwrap();
+            doc.set_options(SvgOptions::compact());
+            for l in &lines().lines {
+                doc.add(l).unwrap();
+            }
+        }
+        let out = String::from_utf8(out).unwrap();
+        assert_eq!(
+            out.lines().nth(1).unwrap(),
+            "<path stroke=\"black\" fill=\"transparent\" d=\"m10 10 10 0 .5-.75m-20.5-9.25 1 1\"/>"
+        );
+        let imported = crate::svg_import::import_svg(&out).unwrap();
+        assert_eq!(imported.lines.len(), 2);
+        assert_eq!(imported.lines[0].ps, lines().lines[0].ps);
+        assert_eq!(imported.lines[1].ps, lines().lines[1].ps);
     }
 }
@@ -0,0 +1,991 @@
+use crate::{
+    attributes::{AttributeRange, AttributeReverse, AttributeSVGLine},
+    n2::{cubic_bezier::CubicBezierPath, lineset::LineSet, point::p2, point::Point},
+    nbase::polyline::{PolyLine, PolyLineAttribute},
+    style::{Colour, StrokeStyle},
+};
+
+#[derive(Debug)]
+pub enum SvgImportError {
+    Io(std::io::Error),
+    UnexpectedEof,
+    InvalidNumber(String),
+    InvalidPathData(String),
+    InvalidTransform(String),
+}
+
+impl From<std::io::Error> for SvgImportError {
+    fn from(e: std::io::Error) -> Self {
+        SvgImportError::Io(e)
+    }
+}
+
+// The style information we keep from imported elements.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ImportedStyle {
+    pub stroke: Option<String>,
+}
+
+impl AttributeSVGLine for ImportedStyle {
+    fn stroke_style(&self) -> StrokeStyle {
+        match &self.stroke {
+            Some(stroke) => StrokeStyle::new(Colour::parse(stroke)),
+            None => StrokeStyle::default(),
+        }
+    }
+}
+
+impl AttributeReverse for ImportedStyle {
+    fn reverse(&self) -> Self {
+        self.clone()
+    }
+}
+
+impl AttributeRange for ImportedStyle {
+    fn range(&self, _t0: f32, _t1: f32) -> Self {
+        self.clone()
+    }
+}
+
+impl PolyLineAttribute for ImportedStyle {
+    type LineAttribute = ImportedStyle;
+    fn attribute_for_line_segment(&self, _index: usize) -> Self::LineAttribute {
+        self.clone()
+    }
+    fn poly_range(&self, _start: (usize, f32), _end: (usize, f32)) -> Self {
+        self.clone()
+    }
+}
+
+// Everything drawable found in an SVG file.
+//
+// Subpaths made only of straight segments become `lines`, anything
+// containing a curve or arc becomes one of the `curves`, with its
+// straight segments converted to (degenerate) cubics.
+#[derive(Clone, Default)]
+pub struct SvgImport {
+    pub lines: Vec<PolyLine<2, ImportedStyle>>,
+    pub curves: Vec<(CubicBezierPath, ImportedStyle)>,
+}
+
+impl SvgImport {
+    pub fn line_set(&self) -> LineSet {
+        LineSet {
+            lines: self.lines.iter().map(|l| l.map_attribute(|_| ())).collect(),
+        }
+    }
+
+    // Only the lines drawn with the given stroke colour.
+    pub fn line_set_with_stroke(&self, stroke: &str) -> LineSet {
+        LineSet {
+            lines: self
+                .lines
+                .iter()
+                .filter(|l| l.attributes.stroke.as_deref() == Some(stroke))
+                .map(|l| l.map_attribute(|_| ()))
+                .collect(),
+        }
+    }
+}
+
+pub fn read_svg<R>(r: &mut R) -> Result<SvgImport, SvgImportError>
+where
+    R: std::io::Read,
+{
+    let mut s = String::new();
+    r.read_to_string(&mut s)?;
+    import_svg(&s)
+}
+
+pub fn import_svg(s: &str) -> Result<SvgImport, SvgImportError> {
+    let mut result = SvgImport::default();
+    let mut stack = vec![Context {
+        transform: Transform::identity(),
+        style: ImportedStyle::default(),
+        skip: false,
+    }];
+    let mut xml = XmlScanner { s, i: 0 };
+    while let Some(event) = xml.next_event()? {
+        match event {
+            XmlEvent::Start {
+                name,
+                attributes,
+                self_closing,
+            } => {
+                let parent = stack.last().unwrap();
+                let context = Context {
+                    transform: match attribute(&attributes, "transform") {
+                        Some(t) => parent.transform.then(&parse_transform(t)?),
+                        None => parent.transform,
+                    },
+                    style: ImportedStyle {
+                        stroke: element_stroke(&attributes).or_else(|| parent.style.stroke.clone()),
+                    },
+                    skip: parent.skip || is_non_rendering(name),
+                };
+                if !context.skip {
+                    import_element(name, &attributes, &context, &mut result)?;
+                }
+                if !self_closing {
+                    stack.push(context);
+                }
+            }
+            XmlEvent::End => {
+                if stack.len() > 1 {
+                    stack.pop();
+                }
+            }
+        }
+    }
+    Ok(result)
+}
+
+struct Context {
+    transform: Transform,
+    style: ImportedStyle,
+    skip: bool,
+}
+
+fn is_non_rendering(name: &str) -> bool {
+    matches!(
+        name,
+        "defs" | "clipPath" | "mask" | "symbol" | "marker" | "pattern" | "style" | "metadata"
+    )
+}
+
+fn attribute<'a>(attributes: &'a [(&str, String)], name: &str) -> Option<&'a str> {
+    attributes
+        .iter()
+        .find(|(k, _)| *k == name)
+        .map(|(_, v)| v.as_str())
+}
+
+// The stroke set directly on an element, with `style` taking
+// precedence over the presentation attribute.
+fn element_stroke(attributes: &[(&str, String)]) -> Option<String> {
+    let from_style = attribute(attributes, "style").and_then(|style| {
+        style.split(';').find_map(|decl| {
+            let (k, v) = decl.split_once(':')?;
+            if k.trim() == "stroke" {
+                Some(v.trim().to_string())
+            } else {
+                None
+            }
+        })
+    });
+    from_style.or_else(|| attribute(attributes, "stroke").map(|v| v.trim().to_string()))
+}
+
+fn length(attributes: &[(&str, String)], name: &str) -> Result<Option<f32>, SvgImportError> {
+    match attribute(attributes, name) {
+        None => Ok(None),
+        Some(v) => {
+            // Units are ignored, everything is treated as user units.
+            let mut lexer = PathLexer::new(v);
+            Ok(Some(lexer.number()?))
+        }
+    }
+}
+
+fn import_element(
+    name: &str,
+    attributes: &[(&str, String)],
+    context: &Context,
+    result: &mut SvgImport,
+) -> Result<(), SvgImportError> {
+    let mut path = PathBuilder::new(context, result);
+    let len =
+        |n: &str| -> Result<f32, SvgImportError> { Ok(length(attributes, n)?.unwrap_or(0.0)) };
+    match name {
+        "path" => {
+            if let Some(d) = attribute(attributes, "d") {
+                parse_path_data(d, &mut path)?;
+            }
+        }
+        "line" => {
+            path.move_to(p2(len("x1")?, len("y1")?));
+            path.line_to(p2(len("x2")?, len("y2")?));
+        }
+        "polyline" | "polygon" => {
+            let mut lexer = PathLexer::new(attribute(attributes, "points").unwrap_or(""));
+            let mut first = true;
+            while lexer.has_number() {
+                let p = p2(lexer.number()?, lexer.number()?);
+                if first {
+                    path.move_to(p);
+                    first = false;
+                } else {
+                    path.line_to(p);
+                }
+            }
+            if name == "polygon" {
+                path.close();
+            }
+        }
+        "rect" => {
+            let (x, y, w, h) = (len("x")?, len("y")?, len("width")?, len("height")?);
+            if w <= 0.0 || h <= 0.0 {
+                return Ok(());
+            }
+            let (rx, ry) = match (length(attributes, "rx")?, length(attributes, "ry")?) {
+                (None, None) => (0.0, 0.0),
+                (Some(rx), None) => (rx, rx),
+                (None, Some(ry)) => (ry, ry),
+                (Some(rx), Some(ry)) => (rx, ry),
+            };
+            let rx = rx.clamp(0.0, w / 2.0);
+            let ry = ry.clamp(0.0, h / 2.0);
+            if rx > 0.0 && ry > 0.0 {
+                path.move_to(p2(x + rx, y));
+                path.line_to(p2(x + w - rx, y));
+                path.arc_to(rx, ry, 0.0, false, true, p2(x + w, y + ry));
+                path.line_to(p2(x + w, y + h - ry));
+                path.arc_to(rx, ry, 0.0, false, true, p2(x + w - rx, y + h));
+                path.line_to(p2(x + rx, y + h));
+                path.arc_to(rx, ry, 0.0, false, true, p2(x, y + h - ry));
+                path.line_to(p2(x, y + ry));
+                path.arc_to(rx, ry, 0.0, false, true, p2(x + rx, y));
+            } else {
+                path.move_to(p2(x, y));
+                path.line_to(p2(x + w, y));
+                path.line_to(p2(x + w, y + h));
+                path.line_to(p2(x, y + h));
+            }
+            path.close();
+        }
+        "circle" | "ellipse" => {
+            let (cx, cy) = (len("cx")?, len("cy")?);
+            let (rx, ry) = if name == "circle" {
+                (len("r")?, len("r")?)
+            } else {
+                (len("rx")?, len("ry")?)
+            };
+            if rx > 0.0 && ry > 0.0 {
+                path.move_to(p2(cx + rx, cy));
+                path.arc_to(rx, ry, 0.0, false, true, p2(cx, cy + ry));
+                path.arc_to(rx, ry, 0.0, false, true, p2(cx - rx, cy));
+                path.arc_to(rx, ry, 0.0, false, true, p2(cx, cy - ry));
+                path.arc_to(rx, ry, 0.0, false, true, p2(cx + rx, cy));
+                path.close();
+            }
+        }
+        _ => {}
+    }
+    path.flush();
+    Ok(())
+}
+
+// An affine transform [a b c d e f] mapping (x,y) to
+// (a x + c y + e, b x + d y + f), as in SVG's matrix().
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Transform([f32; 6]);
+
+impl Transform {
+    fn identity() -> Transform {
+        Transform([1.0, 0.0, 0.0, 1.0, 0.0, 0.0])
+    }
+
+    // The transform that applies `other` first, then `self`.
+    fn then(&self, other: &Transform) -> Transform {
+        let [a, b, c, d, e, f] = self.0;
+        let [a2, b2, c2, d2, e2, f2] = other.0;
+        Transform([
+            a * a2 + c * b2,
+            b * a2 + d * b2,
+            a * c2 + c * d2,
+            b * c2 + d * d2,
+            a * e2 + c * f2 + e,
+            b * e2 + d * f2 + f,
+        ])
+    }
+
+    fn apply(&self, p: Point) -> Point {
+        let [a, b, c, d, e, f] = self.0;
+        let [x, y] = p.vs;
+        p2(a * x + c * y + e, b * x + d * y + f)
+    }
+}
+
+fn parse_transform(s: &str) -> Result<Transform, SvgImportError> {
+    let err = || SvgImportError::InvalidTransform(s.to_string());
+    let mut result = Transform::identity();
+    let mut rest = s.trim();
+    while !rest.is_empty() {
+        let open = rest.find('(').ok_or_else(err)?;
+        let close = rest.find(')').ok_or_else(err)?;
+        let name = rest[..open].trim().trim_start_matches(',').trim();
+        let mut lexer = PathLexer::new(&rest[open + 1..close]);
+        let mut args = vec![];
+        while lexer.has_number() {
+            args.push(lexer.number()?);
+        }
+        let t = match (name, args.as_slice()) {
+            ("matrix", [a, b, c, d, e, f]) => Transform([*a, *b, *c, *d, *e, *f]),
+            ("translate", [x]) => Transform([1.0, 0.0, 0.0, 1.0, *x, 0.0]),
+            ("translate", [x, y]) => Transform([1.0, 0.0, 0.0, 1.0, *x, *y]),
+            ("scale", [s]) => Transform([*s, 0.0, 0.0, *s, 0.0, 0.0]),
+            ("scale", [sx, sy]) => Transform([*sx, 0.0, 0.0, *sy, 0.0, 0.0]),
+            ("rotate", [a]) => rotation(*a),
+            ("rotate", [a, cx, cy]) => Transform([1.0, 0.0, 0.0, 1.0, *cx, *cy])
+                .then(&rotation(*a))
+                .then(&Transform([1.0, 0.0, 0.0, 1.0, -cx, -cy])),
+            ("skewX", [a]) => Transform([1.0, 0.0, a.to_radians().tan(), 1.0, 0.0, 0.0]),
+            ("skewY", [a]) => Transform([1.0, a.to_radians().tan(), 0.0, 1.0, 0.0, 0.0]),
+            _ => return Err(err()),
+        };
+        result = result.then(&t);
+        rest = rest[close + 1..].trim();
+    }
+    Ok(result)
+}
+
+fn rotation(degrees: f32) -> Transform {
+    let (s, c) = degrees.to_radians().sin_cos();
+    Transform([c, s, -s, c, 0.0, 0.0])
+}
+
+enum PathSegment {
+    Line(Point),
+    Cubic(Point, Point, Point),
+}
+
+// Accumulates the segments of the current subpath (in local coordinates)
+// and turns them into lines or curves when the subpath ends.
+struct PathBuilder<'a> {
+    transform: Transform,
+    style: ImportedStyle,
+    result: &'a mut SvgImport,
+    start: Point,
+    current: Point,
+    segments: Vec<PathSegment>,
+    closed: bool,
+}
+
+impl<'a> PathBuilder<'a> {
+    fn new(context: &Context, result: &'a mut SvgImport) -> PathBuilder<'a> {
+        PathBuilder {
+            transform: context.transform,
+            style: context.style.clone(),
+            result,
+            start: Point::zero(),
+            current: Point::zero(),
+            segments: vec![],
+            closed: false,
+        }
+    }
+
+    fn move_to(&mut self, p: Point) {
+        self.flush();
+        self.start = p;
+        self.current = p;
+    }
+
+    fn line_to(&mut self, p: Point) {
+        self.segments.push(PathSegment::Line(p));
+        self.current = p;
+    }
+
+    fn cubic_to(&mut self, c1: Point, c2: Point, p: Point) {
+        self.segments.push(PathSegment::Cubic(c1, c2, p));
+        self.current = p;
+    }
+
+    // Elliptical arc, following the SVG implementation notes (F.6.5),
+    // approximated by one cubic per quarter turn or less.
+    fn arc_to(&mut self, rx: f32, ry: f32, phi: f32, large_arc: bool, sweep: bool, p: Point) {
+        let p0 = self.current;
+        if p0 == p {
+            return;
+        }
+        let (mut rx, mut ry) = (rx.abs(), ry.abs());
+        if rx == 0.0 || ry == 0.0 {
+            self.line_to(p);
+            return;
+        }
+        let (sin_phi, cos_phi) = phi.to_radians().sin_cos();
+        let h = (p0 - p) * 0.5;
+        let x1p = cos_phi * h.vs[0] + sin_phi * h.vs[1];
+        let y1p = -sin_phi * h.vs[0] + cos_phi * h.vs[1];
+        let lambda = (x1p * x1p) / (rx * rx) + (y1p * y1p) / (ry * ry);
+        if lambda > 1.0 {
+            rx *= lambda.sqrt();
+            ry *= lambda.sqrt();
+        }
+        let num = rx * rx * ry * ry - rx * rx * y1p * y1p - ry * ry * x1p * x1p;
+        let den = rx * rx * y1p * y1p + ry * ry * x1p * x1p;
+        let mut coef = (num / den).max(0.0).sqrt();
+        if large_arc == sweep {
+            coef = -coef;
+        }
+        let cxp = coef * rx * y1p / ry;
+        let cyp = -coef * ry * x1p / rx;
+        let mid = (p0 + p) * 0.5;
+        let center = p2(
+            cos_phi * cxp - sin_phi * cyp + mid.vs[0],
+            sin_phi * cxp + cos_phi * cyp + mid.vs[1],
+        );
+
+        let angle =
+            |ux: f32, uy: f32, vx: f32, vy: f32| (ux * vy - uy * vx).atan2(ux * vx + uy * vy);
+        let ux = (x1p - cxp) / rx;
+        let uy = (y1p - cyp) / ry;
+        let vx = (-x1p - cxp) / rx;
+        let vy = (-y1p - cyp) / ry;
+        let theta1 = angle(1.0, 0.0, ux, uy);
+        let mut dtheta = angle(ux, uy, vx, vy);
+        if !sweep && dtheta > 0.0 {
+            dtheta -= 2.0 * std::f32::consts::PI;
+        } else if sweep && dtheta < 0.0 {
+            dtheta += 2.0 * std::f32::consts::PI;
+        }
+
+        let n = (dtheta.abs() / std::f32::consts::FRAC_PI_2).ceil().max(1.0) as usize;
+        let delta = dtheta / n as f32;
+        let k = 4.0 / 3.0 * (delta / 4.0).tan();
+        let to_ellipse = |ux: f32, uy: f32| {
+            center
+                + p2(
+                    rx * ux * cos_phi - ry * uy * sin_phi,
+                    rx * ux * sin_phi + ry * uy * cos_phi,
+                )
+        };
+        for i in 0..n {
+            let t1 = theta1 + delta * i as f32;
+            let t2 = t1 + delta;
+            let (s1, c1) = t1.sin_cos();
+            let (s2, c2) = t2.sin_cos();
+            let end = if i + 1 == n { p } else { to_ellipse(c2, s2) };
+            self.cubic_to(
+                to_ellipse(c1 - k * s1, s1 + k * c1),
+                to_ellipse(c2 + k * s2, s2 - k * c2),
+                end,
+            );
+        }
+    }
+
+    fn close(&mut self) {
+        if self.current != self.start {
+            self.line_to(self.start);
+        }
+        self.closed = true;
+        self.flush();
+        self.current = self.start;
+    }
+
+    fn flush(&mut self) {
+        if self.segments.is_empty() {
+            return;
+        }
+        let segments = std::mem::take(&mut self.segments);
+        let closed = std::mem::take(&mut self.closed);
+        let t = self.transform;
+        if segments.iter().all(|s| matches!(s, PathSegment::Line(_))) {
+            let mut ps = vec![t.apply(self.start)];
+            for s in &segments {
+                if let PathSegment::Line(p) = s {
+                    ps.push(t.apply(*p));
+                }
+            }
+            // The closing segment is implied by the flag.
+            if closed && ps.len() > 2 {
+                ps.pop();
+            }
+            self.result.lines.push(PolyLine {
+                ps,
+                attributes: self.style.clone(),
+                closed,
+            });
+        } else {
+            let mut ps = vec![t.apply(self.start)];
+            let mut last = self.start;
+            for s in &segments {
+                match s {
+                    PathSegment::Line(p) => {
+                        ps.push(t.apply(Point::lerp(1.0 / 3.0, last, *p)));
+                        ps.push(t.apply(Point::lerp(2.0 / 3.0, last, *p)));
+                        ps.push(t.apply(*p));
+                        last = *p;
+                    }
+                    PathSegment::Cubic(c1, c2, p) => {
+                        ps.push(t.apply(*c1));
+                        ps.push(t.apply(*c2));
+                        ps.push(t.apply(*p));
+                        last = *p;
+                    }
+                }
+            }
+            self.result
+                .curves
+                .push((CubicBezierPath { ps }, self.style.clone()));
+        }
+    }
+}
+
+fn parse_path_data(d: &str, path: &mut PathBuilder) -> Result<(), SvgImportError> {
+    let mut lexer = PathLexer::new(d);
+    let mut command: Option<u8> = None;
+    // The control point to reflect for S/T, if the previous command allows it.
+    let mut last_cubic_ctrl: Option<Point> = None;
+    let mut last_quad_ctrl: Option<Point> = None;
+
+    loop {
+        let c = match lexer.command() {
+            Some(c) => c,
+            None => {
+                if !lexer.has_number() {
+                    if lexer.at_end() {
+                        break;
+                    }
+                    return Err(SvgImportError::InvalidPathData(d.to_string()));
+                }
+                // Implicit repetition of the previous command, where
+                // extra pairs after a moveto are treated as lineto.
+                match command {
+                    Some(b'M') => b'L',
+                    Some(b'm') => b'l',
+                    Some(b'Z') | Some(b'z') | None => {
+                        return Err(SvgImportError::InvalidPathData(d.to_string()))
+                    }
+                    Some(c) => c,
+                }
+            }
+        };
+        command = Some(c);
+
+        let relative = c.is_ascii_lowercase();
+        let base = if relative {
+            path.current
+        } else {
+            Point::zero()
+        };
+        let point = |lexer: &mut PathLexer| -> Result<Point, SvgImportError> {
+            Ok(base + p2(lexer.number()?, lexer.number()?))
+        };
+
+        let mut cubic_ctrl = None;
+        let mut quad_ctrl = None;
+        match c.to_ascii_uppercase() {
+            b'M' => {
+                let p = point(&mut lexer)?;
+                path.move_to(p);
+            }
+            b'L' => {
+                let p = point(&mut lexer)?;
+                path.line_to(p);
+            }
+            b'H' => {
+                let x = lexer.number()? + if relative { path.current.vs[0] } else { 0.0 };
+                path.line_to(p2(x, path.current.vs[1]));
+            }
+            b'V' => {
+                let y = lexer.number()? + if relative { path.current.vs[1] } else { 0.0 };
+                path.line_to(p2(path.current.vs[0], y));
+            }
+            b'C' => {
+                let c1 = point(&mut lexer)?;
+                let c2 = point(&mut lexer)?;
+                let p = point(&mut lexer)?;
+                path.cubic_to(c1, c2, p);
+                cubic_ctrl = Some(c2);
+            }
+            b'S' => {
+                let c1 = match last_cubic_ctrl {
+                    Some(c) => path.current * 2.0 - c,
+                    None => path.current,
+                };
+                let c2 = point(&mut lexer)?;
+                let p = point(&mut lexer)?;
+                path.cubic_to(c1, c2, p);
+                cubic_ctrl = Some(c2);
+            }
+            b'Q' => {
+                let q = point(&mut lexer)?;
+                let p = point(&mut lexer)?;
+                quadratic_to(path, q, p);
+                quad_ctrl = Some(q);
+            }
+            b'T' => {
+                let q = match last_quad_ctrl {
+                    Some(q) => path.current * 2.0 - q,
+                    None => path.current,
+                };
+                let p = point(&mut lexer)?;
+                quadratic_to(path, q, p);
+                quad_ctrl = Some(q);
+            }
+            b'A' => {
+                let rx = lexer.number()?;
+                let ry = lexer.number()?;
+                let phi = lexer.number()?;
+                let large_arc = lexer.flag()?;
+                let sweep = lexer.flag()?;
+                let p = point(&mut lexer)?;
+                path.arc_to(rx, ry, phi, large_arc, sweep, p);
+            }
+            b'Z' => {
+                path.close();
+            }
+            _ => return Err(SvgImportError::InvalidPathData(d.to_string())),
+        }
+        last_cubic_ctrl = cubic_ctrl;
+        last_quad_ctrl = quad_ctrl;
+    }
+    Ok(())
+}
+
+fn quadratic_to(path: &mut PathBuilder, q: Point, p: Point) {
+    let p0 = path.current;
+    path.cubic_to(
+        Point::lerp(2.0 / 3.0, p0, q),
+        Point::lerp(2.0 / 3.0, p, q),
+        p,
+    );
+}
+
+// Splits path data, point lists and transform arguments into
+// numbers and command letters.
+struct PathLexer<'a> {
+    s: &'a [u8],
+    i: usize,
+}
+
+impl<'a> PathLexer<'a> {
+    fn new(s: &'a str) -> PathLexer<'a> {
+        PathLexer {
+            s: s.as_bytes(),
+            i: 0,
+        }
+    }
+
+    fn skip_separators(&mut self) {
+        while self.i < self.s.len()
+            && (self.s[self.i].is_ascii_whitespace() || self.s[self.i] == b',')
+        {
+            self.i += 1;
+        }
+    }
+
+    fn at_end(&mut self) -> bool {
+        self.skip_separators();
+        self.i >= self.s.len()
+    }
+
+    fn command(&mut self) -> Option<u8> {
+        self.skip_separators();
+        match self.s.get(self.i) {
+            Some(c) if c.is_ascii_alphabetic() && *c != b'e' && *c != b'E' => {
+                self.i += 1;
+                Some(*c)
+            }
+            _ => None,
+        }
+    }
+
+    fn has_number(&mut self) -> bool {
+        self.skip_separators();
+        matches!(self.s.get(self.i), Some(c) if c.is_ascii_digit() || *c == b'.' || *c == b'-' || *c == b'+')
+    }
+
+    fn number(&mut self) -> Result<f32, SvgImportError> {
+        self.skip_separators();
+        let start = self.i;
+        let digits = |l: &mut Self| {
+            while l.i < l.s.len() && l.s[l.i].is_ascii_digit() {
+                l.i += 1;
+            }
+        };
+        if matches!(self.s.get(self.i), Some(b'-') | Some(b'+')) {
+            self.i += 1;
+        }
+        digits(self);
+        if self.s.get(self.i) == Some(&b'.') {
+            self.i += 1;
+            digits(self);
+        }
+        if matches!(self.s.get(self.i), Some(b'e') | Some(b'E')) {
+            let mark = self.i;
+            self.i += 1;
+            if matches!(self.s.get(self.i), Some(b'-') | Some(b'+')) {
+                self.i += 1;
+            }
+            if matches!(self.s.get(self.i), Some(c) if c.is_ascii_digit()) {
+                digits(self);
+            } else {
+                self.i = mark;
+            }
+        }
+        let text = std::str::from_utf8(&self.s[start..self.i]).unwrap();
+        if text.is_empty() {
+            return Err(if self.i >= self.s.len() {
+                SvgImportError::UnexpectedEof
+            } else {
+                SvgImportError::InvalidNumber(String::from_utf8_lossy(&self.s[start..]).to_string())
+            });
+        }
+        text.parse()
+            .map_err(|_| SvgImportError::InvalidNumber(text.to_string()))
+    }
+
+    // Arc flags are a single 0 or 1 and need not be separated
+    // from what follows, e.g. "a1 1 0 01 1 1".
+    fn flag(&mut self) -> Result<bool, SvgImportError> {
+        self.skip_separators();
+        let result = match self.s.get(self.i) {
+            Some(b'0') => false,
+            Some(b'1') => true,
+            Some(_) => {
+                return Err(SvgImportError::InvalidPathData(
+                    String::from_utf8_lossy(&self.s[self.i..]).to_string(),
+                ))
+            }
+            None => return Err(SvgImportError::UnexpectedEof),
+        };
+        self.i += 1;
+        Ok(result)
+    }
+}
+
+enum XmlEvent<'a> {
+    Start {
+        name: &'a str,
+        attributes: Vec<(&'a str, String)>,
+        self_closing: bool,
+    },
+    End,
+}
+
+// Just enough XML to walk the element tree of an SVG file.
+// Text content, comments, processing instructions and DOCTYPEs are skipped.
+struct XmlScanner<'a> {
+    s: &'a str,
+    i: usize,
+}
+
+impl<'a> XmlScanner<'a> {
+    fn skip_past(&mut self, pattern: &str) -> Result<(), SvgImportError> {
+        match self.s[self.i..].find(pattern) {
+            Some(n) => {
+                self.i += n + pattern.len();
+                Ok(())
+            }
+            None => Err(SvgImportError::UnexpectedEof),
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = &self.s[self.i..];
+        self.i += rest.len() - rest.trim_start().len();
+    }
+
+    fn name(&mut self) -> &'a str {
+        let rest = &self.s[self.i..];
+        let n = rest
+            .find(|c: char| c.is_whitespace() || c == '/' || c == '>' || c == '=')
+            .unwrap_or(rest.len());
+        self.i += n;
+        let name = &rest[..n];
+        // Drop any namespace prefix, e.g. "svg:path"
+        match name.rsplit_once(':') {
+            Some((_, local)) => local,
+            None => name,
+        }
+    }
+
+    fn next_event(&mut self) -> Result<Option<XmlEvent<'a>>, SvgImportError> {
+        loop {
+            match self.s[self.i..].find('<') {
+                None => return Ok(None),
+                Some(n) => self.i += n,
+            }
+            let rest = &self.s[self.i..];
+            if rest.starts_with("<!--") {
+                self.skip_past("-->")?;
+            } else if rest.starts_with("<![CDATA[") {
+                self.skip_past("]]>")?;
+            } else if rest.starts_with("<?") {
+                self.skip_past("?>")?;
+            } else if rest.starts_with("<!") {
+                let end = rest.find('>').ok_or(SvgImportError::UnexpectedEof)?;
+                if rest[..end].contains('[') {
+                    self.skip_past("]>")?;
+                } else {
+                    self.i += end + 1;
+                }
+            } else if rest.starts_with("</") {
+                self.skip_past(">")?;
+                return Ok(Some(XmlEvent::End));
+            } else {
+                self.i += 1;
+                return self.start_tag().map(Some);
+            }
+        }
+    }
+
+    fn start_tag(&mut self) -> Result<XmlEvent<'a>, SvgImportError> {
+        let name = self.name();
+        let mut attributes = vec![];
+        loop {
+            self.skip_whitespace();
+            let rest = &self.s[self.i..];
+            if rest.is_empty() {
+                return Err(SvgImportError::UnexpectedEof);
+            }
+            if rest.starts_with("/>") {
+                self.i += 2;
+                return Ok(XmlEvent::Start {
+                    name,
+                    attributes,
+                    self_closing: true,
+                });
+            }
+            if rest.starts_with('>') {
+                self.i += 1;
+                return Ok(XmlEvent::Start {
+                    name,
+                    attributes,
+                    self_closing: false,
+                });
+            }
+            let key = self.name();
+            self.skip_whitespace();
+            if !self.s[self.i..].starts_with('=') {
+                // Attribute without a value, or a stray character.
+                if key.is_empty() {
+                    self.i += 1;
+                }
+                continue;
+            }
+            self.i += 1;
+            self.skip_whitespace();
+            let quote = self.s[self.i..]
+                .chars()
+                .next()
+                .ok_or(SvgImportError::UnexpectedEof)?;
+            if quote != '"' && quote != '\'' {
+                return Err(SvgImportError::InvalidPathData(
+                    self.s[self.i..].chars().take(20).collect(),
+                ));
+            }
+            self.i += 1;
+            let end = self.s[self.i..]
+                .find(quote)
+                .ok_or(SvgImportError::UnexpectedEof)?;
+            attributes.push((key, decode_entities(&self.s[self.i..self.i + end])));
+            self.i += end + 1;
+        }
+    }
+}
+
+fn decode_entities(s: &str) -> String {
+    if !s.contains('&') {
+        return s.to_string();
+    }
+    let mut result = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(n) = rest.find('&') {
+        result.push_str(&rest[..n]);
+        rest = &rest[n..];
+        let end = match rest.find(';') {
+            Some(end) => end,
+            None => break,
+        };
+        let entity = &rest[1..end];
+        let decoded = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => {
+                if let Some(hex) = entity.strip_prefix("#x") {
+                    u32::from_str_radix(hex, 16).ok().and_then(char::from_u32)
+                } else if let Some(dec) = entity.strip_prefix('#') {
+                    dec.parse().ok().and_then(char::from_u32)
+                } else {
+                    None
+                }
+            }
+        };
+        match decoded {
+            Some(c) => {
+                result.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                result.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    fn close(a: Point, b: Point) -> bool {
+        (a - b).abs().max() < 1e-3
+    }
+
+    #[test]
+    pub fn relative_path_commands() {
+        let svg = r#"<svg><path stroke="red" d="m10 10h5v5l-5,-5zM0 0L1 1"/></svg>"#;
+        let import = import_svg(svg).unwrap();
+        assert_eq!(import.lines.len(), 2);
+        let ps = &import.lines[0].ps;
+        assert_eq!(ps, &vec![p2(10.0, 10.0), p2(15.0, 10.0), p2(15.0, 15.0)]);
+        assert!(import.lines[0].closed);
+        assert!(!import.lines[1].closed);
+        assert_eq!(import.lines[0].attributes.stroke.as_deref(), Some("red"));
+        assert_eq!(import.lines[1].ps, vec![p2(0.0, 0.0), p2(1.0, 1.0)]);
+    }
+
+    #[test]
+    pub fn group_transform_and_inherited_stroke() {
+        let svg = r#"<?xml version="1.0"?>
+            <!-- a comment -->
+            <svg xmlns="http://www.w3.org/2000/svg">
+              <g transform="translate(100,0) scale(2)" style="stroke: #00ff00">
+                <line x1="1" y1="2" x2="3" y2="4"/>
+                <polyline points="0,0 1,0 1,1" stroke="blue"/>
+              </g>
+              <defs><path d="M 0 0 L 5 5"/></defs>
+            </svg>"#;
+        let import = import_svg(svg).unwrap();
+        assert_eq!(import.lines.len(), 2);
+        assert_eq!(import.lines[0].ps, vec![p2(102.0, 4.0), p2(106.0, 8.0)]);
+        assert_eq!(
+            import.lines[0].attributes.stroke.as_deref(),
+            Some("#00ff00")
+        );
+        assert_eq!(import.lines[1].attributes.stroke.as_deref(), Some("blue"));
+    }
+
+    #[test]
+    pub fn circle_becomes_four_cubics() {
+        let svg = r#"<svg><circle cx="10" cy="10" r="5"/></svg>"#;
+        let import = import_svg(svg).unwrap();
+        assert_eq!(import.curves.len(), 1);
+        let (path, _) = &import.curves[0];
+        assert_eq!(path.ps.len(), 13);
+        for i in 0..4 {
+            let mid = path.segment(i).value(0.5);
+            assert!(((mid - p2(10.0, 10.0)).norm_squared().sqrt() - 5.0).abs() < 0.01);
+        }
+        assert!(close(path.ps[0], path.ps[12]));
+    }
+
+    #[test]
+    pub fn quadratic_and_arc_endpoints() {
+        let svg = r#"<svg><path d="M0,0 Q5,5 10,0 T20,0 A5 5 0 0 1 30 0"/></svg>"#;
+        let import = import_svg(svg).unwrap();
+        let (path, _) = &import.curves[0];
+        assert!(close(path.ps[3], p2(10.0, 0.0)));
+        assert!(close(path.ps[6], p2(20.0, 0.0)));
+        assert!(close(*path.ps.last().unwrap(), p2(30.0, 0.0)));
+        // The reflected control point of T puts the second hump below the axis.
+        assert!(path.segment(1).value(0.5).vs[1] < 0.0);
+    }
+}